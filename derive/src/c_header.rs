@@ -0,0 +1,337 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generates C headers for `#[tinydyn(repr_c)]` traits.
+
+use proc_macro2::Ident;
+use syn::{ext::IdentExt, fold::Fold, parse_quote, spanned::Spanned, Error, Result};
+
+/// A method of a `repr_c` trait, as declared in C.
+pub(crate) struct CMethod {
+    name: String,
+    ret: String,
+    params: Vec<String>,
+    /// The C types of the method, without parameter names.
+    signature: String,
+}
+
+impl CMethod {
+    pub(crate) fn new(sig: &syn::Signature) -> Result<Self> {
+        let name = sig.ident.unraw().to_string();
+        if is_c_keyword(&name) {
+            return Err(Error::new(
+                sig.ident.span(),
+                format!("`{name}` is a C keyword, so it can't name a method of a `repr_c` trait"),
+            ));
+        }
+        let mut param_types = Vec::new();
+        let params = sig
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(arg_num, arg)| match arg {
+                syn::FnArg::Receiver(receiver) => {
+                    let ty = c_pointer("void".into(), receiver.mutability.is_none());
+                    param_types.push(ty.clone());
+                    Ok(c_declaration(ty, "self"))
+                }
+                syn::FnArg::Typed(pat_type) => {
+                    // Parameter names are only for readers, so C keywords are renamed.
+                    let name = match &*pat_type.pat {
+                        syn::Pat::Ident(pat) if !is_c_keyword(&pat.ident.unraw().to_string()) => {
+                            pat.ident.unraw().to_string()
+                        }
+                        _ => format!("arg{arg_num}"),
+                    };
+                    let ty = c_type(&pat_type.ty)?;
+                    param_types.push(ty.clone());
+                    Ok(c_declaration(ty, &name))
+                }
+            })
+            .collect::<Result<_>>()?;
+        let ret = match &sig.output {
+            syn::ReturnType::Default => "void".to_string(),
+            syn::ReturnType::Type(_, ty) => c_type(ty)?,
+        };
+        let signature = format!("{ret} {name}({})", param_types.join(", "));
+        Ok(Self {
+            name,
+            ret,
+            params,
+            signature,
+        })
+    }
+
+    /// Hashes the name and C types of the method, to check it against another build of it.
+    ///
+    /// This is 64-bit FNV-1a, which is stable across compilers.
+    pub(crate) fn fingerprint(&self) -> u64 {
+        self.signature
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+/// Declares the vtable of `dyn Trait` and its `Ref`/`RefMut` in C.
+///
+/// A stable vtable starts with a `TinydynAbiHeader header` holding its size and version, which
+/// is shared by every tinydyn header.
+/// An inline vtable is stored by value in the `Ref`, rather than by pointer.
+pub(crate) fn c_header(
+    trait_ident: &Ident,
+    methods: &[CMethod],
+    stable_abi: bool,
+    inline: bool,
+) -> String {
+    let guard = format!("TINYDYN_{}_H", trait_ident.to_string().to_uppercase());
+    let vtable = format!("{trait_ident}Vtable");
+    let vtable_field = if inline {
+        format!("{vtable} vtable;")
+    } else {
+        format!("const {vtable} *vtable;")
+    };
+    let mut out = format!(
+        "/* Generated by tinydyn for `dyn {trait_ident}`. */\n\
+        #ifndef {guard}\n\
+        #define {guard}\n\
+        \n\
+        #include <stdbool.h>\n\
+        #include <stddef.h>\n\
+        #include <stdint.h>\n\
+        \n"
+    );
+    if stable_abi {
+        out += "#ifndef TINYDYN_ABI_HEADER\n\
+            #define TINYDYN_ABI_HEADER\n\
+            typedef struct TinydynAbiHeader {\n    \
+                uint32_t size;\n    \
+                uint32_t version;\n\
+            } TinydynAbiHeader;\n\
+            #endif\n\
+            \n";
+    }
+    out += &format!("typedef struct {vtable} {{\n");
+    if stable_abi {
+        out += "    TinydynAbiHeader header;\n";
+    }
+    for CMethod {
+        name, ret, params, ..
+    } in methods
+    {
+        out += &format!("    {ret} (*{name})({});\n", params.join(", "));
+    }
+    out += &format!(
+        "}} {vtable};\n\
+        \n\
+        typedef struct {trait_ident}Ref {{\n    \
+            const void *data;\n    \
+            {vtable_field}\n\
+        }} {trait_ident}Ref;\n\
+        \n\
+        typedef struct {trait_ident}RefMut {{\n    \
+            void *data;\n    \
+            {vtable_field}\n\
+        }} {trait_ident}RefMut;\n\
+        \n\
+        #endif /* {guard} */\n"
+    );
+    out
+}
+
+/// The C spelling of a Rust type.
+///
+/// Only primitives, `core::ffi` types, and pointers to them are known. Other types, even
+/// `#[repr(C)]` ones, aren't declared by the header, so they're rejected.
+///
+/// Imports can't be resolved here, so a single name like `u32` or `c_int` is taken to be the
+/// known type. A longer path must name it in full, like `core::ffi::c_int`.
+fn c_type(ty: &syn::Type) -> Result<String> {
+    Ok(match ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let (module, segment) = split_path(&path.path).ok_or_else(|| unsupported(ty))?;
+            let module: Vec<_> = module.iter().map(String::as_str).collect();
+            match &segment.arguments {
+                syn::PathArguments::None => {
+                    c_type_name(&module, &segment.ident).ok_or_else(|| unsupported(ty))?
+                }
+                syn::PathArguments::AngleBracketed(args)
+                    if segment.ident == "NonNull"
+                        && matches!(module[..], [] | ["core" | "std", "ptr"])
+                        && args.args.len() == 1 =>
+                {
+                    let syn::GenericArgument::Type(pointee) = &args.args[0] else {
+                        return Err(unsupported(ty));
+                    };
+                    c_pointer(c_type(pointee)?, false)
+                }
+                _ => return Err(unsupported(ty)),
+            }
+        }
+        syn::Type::Reference(reference) => {
+            c_pointer(c_type(&reference.elem)?, reference.mutability.is_none())
+        }
+        syn::Type::Ptr(ptr) => c_pointer(c_type(&ptr.elem)?, ptr.const_token.is_some()),
+        syn::Type::Tuple(tuple) if tuple.elems.is_empty() => "void".to_string(),
+        syn::Type::Paren(paren) => c_type(&paren.elem)?,
+        syn::Type::Group(group) => c_type(&group.elem)?,
+        _ => return Err(unsupported(ty)),
+    })
+}
+
+/// Splits `path` into the names of its leading modules and its last segment.
+///
+/// Returns `None` if a leading segment has generic arguments, or a single name starts with `::`.
+fn split_path(path: &syn::Path) -> Option<(Vec<String>, &syn::PathSegment)> {
+    let last = path.segments.last()?;
+    let module = path.segments.iter().take(path.segments.len() - 1);
+    if path.segments.len() == 1 && path.leading_colon.is_some() {
+        return None;
+    }
+    let module = module
+        .map(|segment| {
+            segment
+                .arguments
+                .is_none()
+                .then(|| segment.ident.to_string())
+        })
+        .collect::<Option<_>>()?;
+    Some((module, last))
+}
+
+/// The C spelling of a primitive or `core::ffi` type named `ident` in `module`.
+///
+/// `char` is left out: C could return any `uint32_t`, which may not be a valid `char`.
+fn c_type_name(module: &[&str], ident: &Ident) -> Option<String> {
+    let name = ident.to_string();
+    let primitive = matches!(module, [] | ["core" | "std", "primitive"]);
+    let ffi = matches!(module, [] | ["core" | "std", "ffi"] | ["std", "os", "raw"]);
+    Some(match name.as_str() {
+        "u8" | "u16" | "u32" | "u64" if primitive => format!("uint{}_t", &name[1..]),
+        "i8" | "i16" | "i32" | "i64" if primitive => format!("int{}_t", &name[1..]),
+        "usize" if primitive => "size_t".into(),
+        "isize" if primitive => "ptrdiff_t".into(),
+        "bool" if primitive => "bool".into(),
+        "f32" if primitive => "float".into(),
+        "f64" if primitive => "double".into(),
+        _ if !ffi => return None,
+        "c_void" => "void".into(),
+        "c_char" => "char".into(),
+        "c_schar" => "signed char".into(),
+        "c_uchar" => "unsigned char".into(),
+        "c_short" => "short".into(),
+        "c_ushort" => "unsigned short".into(),
+        "c_int" => "int".into(),
+        "c_uint" => "unsigned int".into(),
+        "c_long" => "long".into(),
+        "c_ulong" => "unsigned long".into(),
+        "c_longlong" => "long long".into(),
+        "c_ulonglong" => "unsigned long long".into(),
+        _ => return None,
+    })
+}
+
+/// The Rust spelling of `ty` as C passes it, with references replaced by raw pointers.
+///
+/// C can't uphold the guarantees of a reference, so `{Trait}CVtable` entries take pointers with
+/// the same ABI instead.
+pub(crate) fn c_rust_type(ty: &syn::Type) -> syn::Type {
+    struct RefsToPtrs;
+    impl Fold for RefsToPtrs {
+        fn fold_type(&mut self, ty: syn::Type) -> syn::Type {
+            match ty {
+                syn::Type::Reference(reference) => {
+                    let elem = self.fold_type(*reference.elem);
+                    if reference.mutability.is_some() {
+                        parse_quote!(*mut #elem)
+                    } else {
+                        parse_quote!(*const #elem)
+                    }
+                }
+                ty => syn::fold::fold_type(self, ty),
+            }
+        }
+    }
+    RefsToPtrs.fold_type(ty.clone())
+}
+
+/// Declares `name` with the C type `ty`.
+fn c_declaration(ty: String, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{ty}{name}")
+    } else {
+        format!("{ty} {name}")
+    }
+}
+
+/// A pointer to `pointee`, which is itself `const` if `is_const`.
+fn c_pointer(pointee: String, is_const: bool) -> String {
+    match (is_const, pointee.ends_with('*')) {
+        (false, _) => format!("{pointee} *"),
+        (true, false) => format!("const {pointee} *"),
+        (true, true) => format!("{pointee} const *"),
+    }
+}
+
+/// Whether `name` is reserved in C, including the `stdbool.h` macros the header includes.
+fn is_c_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "auto"
+            | "bool"
+            | "break"
+            | "case"
+            | "char"
+            | "const"
+            | "continue"
+            | "default"
+            | "do"
+            | "double"
+            | "else"
+            | "enum"
+            | "extern"
+            | "false"
+            | "float"
+            | "for"
+            | "goto"
+            | "if"
+            | "inline"
+            | "int"
+            | "long"
+            | "register"
+            | "restrict"
+            | "return"
+            | "short"
+            | "signed"
+            | "sizeof"
+            | "static"
+            | "struct"
+            | "switch"
+            | "true"
+            | "typedef"
+            | "union"
+            | "unsigned"
+            | "void"
+            | "volatile"
+            | "while"
+    ) || name.starts_with('_') && name.chars().nth(1).is_some_and(|c| c.is_ascii_uppercase())
+}
+
+fn unsupported(ty: &syn::Type) -> Error {
+    Error::new(
+        ty.span(),
+        "this type can't be used in a `repr_c` tinydyn trait",
+    )
+}
@@ -0,0 +1,234 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generates combined trait objects for `combine!`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{punctuated::Punctuated, Error, Result, Token};
+
+use crate::markers::Markers;
+
+/// The arguments to `combine!`, like `pub trait SensorAndConfig = Sensor + Configurable;`.
+pub(crate) struct Combine {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    ident: syn::Ident,
+    parts: Punctuated<syn::Path, Token![+]>,
+}
+
+impl Parse for Combine {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![trait]>()?;
+        let ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let parts = Punctuated::parse_separated_nonempty(input)?;
+        input.parse::<Option<Token![;]>>()?;
+        if parts.len() < 2 {
+            return Err(Error::new_spanned(
+                &parts,
+                "`combine!` needs at least two traits",
+            ));
+        }
+        Ok(Self {
+            attrs,
+            vis,
+            ident,
+            parts,
+        })
+    }
+}
+
+impl Combine {
+    /// Declares the combined trait, with a vtable holding the vtable of each part.
+    pub(crate) fn expand(self) -> TokenStream {
+        let Self {
+            attrs,
+            vis,
+            ident,
+            parts,
+        } = self;
+        let tinydyn = format_ident!("tinydyn");
+        let private = quote!(#tinydyn ::__private);
+        let trait_object = quote!(dyn #ident);
+        let mod_ident = format_ident!("__tinydyn_impl_{ident}");
+        let vtable_ident = format_ident!("{ident}Vtable");
+        let newtype_ident = format_ident!("{ident}Newtype");
+        let parts: Vec<_> = parts.into_iter().collect();
+        let part_objects: Vec<_> = parts.iter().map(|part| quote!(dyn #part)).collect();
+        let part_vtables = part_objects
+            .iter()
+            .map(|part| quote!(<#part as #tinydyn ::PlainDyn>::VTable));
+        let part_newtypes: Vec<_> = part_objects
+            .iter()
+            .map(|part| quote!(<#part as #tinydyn ::PlainDyn>::LocalNewtype<Concrete>))
+            .collect();
+        let first_part = &part_objects[0];
+        let indices = (0..parts.len()).map(syn::Index::from);
+
+        let vtable_doc = format!(
+            " The tinydyn vtable for `dyn {ident}`, holding the vtable of each of its traits."
+        );
+        // Each part has its own marked vtable, for its `where Self: Sync` methods.
+        let marked_parts = |markers: &[&syn::Path]| {
+            part_objects
+                .iter()
+                .map(|part| quote!(#part #(+ #markers)*))
+                .collect::<Vec<_>>()
+        };
+        let marker_impls = Markers::default().impls(
+            &trait_object,
+            &newtype_ident,
+            |markers| {
+                let marked_parts = marked_parts(markers);
+                quote!(#(#part_newtypes: #tinydyn ::Implements<#marked_parts>,)*)
+            },
+            |object, markers| {
+                let marked_parts = marked_parts(markers);
+                quote!(
+                    const MARKED_VTABLE_REF: &'static #vtable_ident = &#vtable_ident(
+                        #(*<#part_newtypes as #tinydyn ::Implements<#marked_parts>>::MARKED_VTABLE_REF,)*
+                    );
+                    const MARKED_METADATA: &'static #vtable_ident =
+                        <Self as #tinydyn ::Implements<#object>>::MARKED_VTABLE_REF;
+                )
+            },
+        );
+        let has_parts = part_objects
+            .iter()
+            .zip(indices.clone())
+            .map(|(part, index)| {
+                quote!(
+                    unsafe impl #private ::HasPart<#part> for #trait_object {
+                        #[inline(always)]
+                        fn part(
+                            meta: &'static #vtable_ident,
+                        ) -> <#part as #tinydyn ::PlainDyn>::Metadata {
+                            <#part as #tinydyn ::PlainDyn>::metadata_from_vtable(&meta.#index)
+                        }
+                    }
+                )
+            });
+
+        // Each part may have a `Debug` or `Display` supertrait, so try them in order.
+        let (fmt_fns, dyn_fmt_traits): (Vec<_>, Vec<_>) = [
+            ("fmt_debug", "DynDebug", "Debug"),
+            ("fmt_display", "DynDisplay", "Display"),
+        ]
+        .into_iter()
+        .map(|(fmt_fn, dyn_trait, fmt_trait)| {
+            let fmt_fn = format_ident!("{fmt_fn}");
+            let dyn_trait = format_ident!("{dyn_trait}");
+            let fmt_trait = format_ident!("{fmt_trait}");
+            let indices = indices.clone();
+            let fmt_fn_tokens = quote!(
+                #[inline(always)]
+                unsafe fn #fmt_fn(
+                    meta: &'static #vtable_ident,
+                    self_: #private ::SelfPtr<*const #trait_object>,
+                    f: &mut core::fmt::Formatter<'_>,
+                ) -> Option<core::fmt::Result> {
+                    #(
+                        let part_meta =
+                            <#part_objects as #tinydyn ::PlainDyn>::metadata_from_vtable(&meta.#indices);
+                        if let Some(result) = unsafe {
+                            <#part_objects as #tinydyn ::PlainDyn>::#fmt_fn(part_meta, self_.part(), f)
+                        } {
+                            return Some(result);
+                        }
+                    )*
+                    None
+                }
+            );
+            // The higher-ranked bound holds only if a part has the supertrait, without being
+            // rejected as trivially false otherwise.
+            let dyn_fmt_trait = quote!(
+                unsafe impl #tinydyn ::#dyn_trait for #trait_object
+                where
+                    for<'a> #trait_object + 'a: core::fmt::#fmt_trait,
+                {
+                }
+            );
+            (fmt_fn_tokens, dyn_fmt_trait)
+        })
+        .unzip();
+
+        quote!(
+            #(#attrs)*
+            #vis trait #ident: #(#parts)+* {}
+
+            impl<T: ?Sized + #(#parts)+*> #ident for T {}
+
+            #vis use #mod_ident::#vtable_ident;
+
+            mod #mod_ident {
+                use super::*;
+
+                #[doc = #vtable_doc]
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub struct #vtable_ident(#(#part_vtables),*);
+
+                #[repr(transparent)]
+                pub struct #newtype_ident <T>(T);
+
+                unsafe impl #tinydyn ::PlainDyn for #trait_object {
+                    type Metadata = &'static #vtable_ident;
+                    type StaticVTable = #vtable_ident;
+                    type VTable = #vtable_ident;
+                    type LocalNewtype<T> = #newtype_ident <T>;
+
+                    #[inline(always)]
+                    fn metadata_from_vtable(vtable: &'static #vtable_ident) -> &'static #vtable_ident {
+                        vtable
+                    }
+
+                    #[inline(always)]
+                    fn vtable_addr(meta: &'static #vtable_ident) -> *const () {
+                        meta as *const #vtable_ident as *const ()
+                    }
+
+                    #[inline(always)]
+                    fn type_name(meta: &'static #vtable_ident) -> Option<&'static str> {
+                        <#first_part as #tinydyn ::PlainDyn>::type_name(
+                            <#first_part as #tinydyn ::PlainDyn>::metadata_from_vtable(&meta.0),
+                        )
+                    }
+
+                    #(#fmt_fns)*
+                }
+
+                #(#dyn_fmt_traits)*
+
+                unsafe impl<Concrete> #tinydyn ::BuildDynMeta<#trait_object> for #newtype_ident <Concrete>
+                where
+                    #(#part_newtypes: #tinydyn ::BuildDynMeta<#part_objects>,)*
+                {
+                    const VTABLE: #vtable_ident = #vtable_ident(
+                        #(<#part_newtypes as #tinydyn ::BuildDynMeta<#part_objects>>::VTABLE,)*
+                    );
+                    const VTABLE_REF: &'static #vtable_ident = &Self::VTABLE;
+                    const STATIC_VTABLE: #vtable_ident = Self::VTABLE;
+                    const METADATA: &'static #vtable_ident = &Self::STATIC_VTABLE;
+                }
+
+                #marker_impls
+
+                #(#has_parts)*
+            }
+        )
+    }
+}
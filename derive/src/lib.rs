@@ -13,9 +13,16 @@
 // limitations under the License.
 
 extern crate proc_macro;
+
+mod c_header;
+mod combine;
+mod markers;
+
+use c_header::CMethod;
+use markers::Markers;
 use proc_macro2::{Ident, Span, TokenStream};
 
-use quote::{format_ident, quote, quote_spanned, ToTokens};
+use quote::{format_ident, quote, ToTokens};
 use syn::{
     parse_macro_input, punctuated::Punctuated, spanned::Spanned, Error, Generics, ItemTrait,
     Result, Token, TraitItem, TraitItemFn, TypeParamBound,
@@ -38,11 +45,40 @@ fn generics_unimplemented(generics: &Generics) -> Result<()> {
     Ok(())
 }
 
-fn supertraits_unimplemented(supertraits: &Punctuated<TypeParamBound, Token![+]>) -> Result<()> {
-    if !supertraits.is_empty() {
-        return Err(unimplemented(&supertraits, "supertraits"));
+/// The `core::fmt` traits that can be supertraits, whose vtable entries format the value.
+const FMT_TRAITS: &[&str] = &["Debug", "Display"];
+
+/// Checks that each supertrait is an auto trait like `Send`, a lifetime, or a [`FMT_TRAITS`]
+/// trait, returning the latter.
+fn supertraits_unimplemented(
+    supertraits: &Punctuated<TypeParamBound, Token![+]>,
+) -> Result<Vec<Ident>> {
+    let mut fmt_traits = Vec::new();
+    for supertrait in supertraits {
+        let supported = match supertrait {
+            TypeParamBound::Lifetime(_) => true,
+            TypeParamBound::Trait(bound)
+                if matches!(bound.modifier, syn::TraitBoundModifier::None)
+                    && bound.lifetimes.is_none() =>
+            {
+                let last = bound.path.segments.last().unwrap();
+                if last.arguments.is_none() && FMT_TRAITS.iter().any(|fmt| last.ident == fmt) {
+                    fmt_traits.push(last.ident.clone());
+                    true
+                } else {
+                    markers::is_auto_trait(&bound.path)
+                }
+            }
+            _ => false,
+        };
+        if !supported {
+            return Err(unimplemented(
+                supertrait,
+                "supertraits other than auto traits, lifetimes, `Debug` and `Display`",
+            ));
+        }
     }
-    Ok(())
+    Ok(fmt_traits)
 }
 
 fn unsafe_trait_unsupported(unsafety: &Option<Token![unsafe]>) -> Result<()> {
@@ -52,6 +88,163 @@ fn unsafe_trait_unsupported(unsafety: &Option<Token![unsafe]>) -> Result<()> {
     Ok(())
 }
 
+/// Options passed to the trait attribute, like `#[tinydyn(eq, hash)]`.
+#[derive(Default)]
+struct TraitArgs {
+    /// `downcast`: the vtable records the concrete `TypeId`, enabling checked downcasts.
+    downcast: bool,
+    /// `partial_eq`: `Ref<dyn Trait>` implements `PartialEq` by comparing concrete values.
+    partial_eq: bool,
+    /// `eq`: like `partial_eq`, but also implements `Eq`.
+    eq: bool,
+    /// `hash`: `Ref<dyn Trait>` implements `Hash` by hashing the concrete value.
+    hash: bool,
+    /// `query`: the vtable has a table of other traits the concrete type provides.
+    query: bool,
+    /// `ref_impls`: `Ref` and `RefMut` implement the trait, calling the vtable directly.
+    ref_impls: bool,
+    /// `markers(Unpin, IsrSafe)`: marker traits the trait object can carry, besides `Send` and
+    /// `Sync`.
+    markers: Markers,
+    /// `repr_c`: the vtable is `repr(C)` with `extern "C"` entries, and has a C header.
+    repr_c: bool,
+    /// `stable_abi(version = N, min_version = M)`: like `repr_c`, but the vtable starts with a
+    /// header, and methods are versioned.
+    stable_abi: Option<StableAbiArgs>,
+    /// The `#[tinydyn(..)]` options of each method, taken from the trait's methods.
+    methods: Vec<MethodArgs>,
+}
+
+/// Options on a trait method, like `#[tinydyn(since = 2)]`.
+#[derive(Default)]
+struct MethodArgs {
+    /// `since = N`: the version of a `stable_abi` trait the method was added in.
+    since: Option<syn::LitInt>,
+    /// `optional`: the vtable only has an entry if the concrete type overrides the method.
+    optional: Option<Span>,
+}
+
+struct StableAbiArgs {
+    version: u32,
+    /// Methods up to this version are required when importing a vtable.
+    min_version: u32,
+}
+
+impl TraitArgs {
+    fn parse(params: TokenStream) -> Result<Self> {
+        let mut args = Self::default();
+        let parser = syn::meta::parser(|meta| {
+            let flag = if meta.path.is_ident("downcast") {
+                &mut args.downcast
+            } else if meta.path.is_ident("partial_eq") {
+                &mut args.partial_eq
+            } else if meta.path.is_ident("eq") {
+                &mut args.eq
+            } else if meta.path.is_ident("hash") {
+                &mut args.hash
+            } else if meta.path.is_ident("query") {
+                &mut args.query
+            } else if meta.path.is_ident("ref_impls") {
+                &mut args.ref_impls
+            } else if meta.path.is_ident("repr_c") {
+                &mut args.repr_c
+            } else if meta.path.is_ident("markers") {
+                meta.parse_nested_meta(|meta| args.markers.add(meta.path))?;
+                return Ok(());
+            } else if meta.path.is_ident("stable_abi") {
+                if args.stable_abi.is_some() {
+                    return Err(meta.error("duplicate tinydyn option"));
+                }
+                let mut version = None;
+                let mut min_version = None;
+                meta.parse_nested_meta(|meta| {
+                    let value = if meta.path.is_ident("version") {
+                        &mut version
+                    } else if meta.path.is_ident("min_version") {
+                        &mut min_version
+                    } else {
+                        return Err(meta.error("unknown stable_abi option"));
+                    };
+                    let lit: syn::LitInt = meta.value()?.parse()?;
+                    *value = Some(lit.base10_parse::<u32>()?);
+                    Ok(())
+                })?;
+                let Some(version) = version else {
+                    return Err(meta.error("stable_abi requires a `version`"));
+                };
+                let min_version = min_version.unwrap_or(1);
+                if min_version == 0 || min_version > version {
+                    return Err(meta.error("`min_version` must be from 1 to `version`"));
+                }
+                args.stable_abi = Some(StableAbiArgs {
+                    version,
+                    min_version,
+                });
+                return Ok(());
+            } else {
+                return Err(meta.error("unknown tinydyn option"));
+            };
+            if *flag {
+                return Err(meta.error("duplicate tinydyn option"));
+            }
+            *flag = true;
+            Ok(())
+        });
+        syn::parse::Parser::parse2(parser, params.clone())?;
+        if args.repr_c && args.stable_abi.is_some() {
+            return Err(Error::new_spanned(
+                params,
+                "`stable_abi` already implies `repr_c`",
+            ));
+        }
+        let repr_c = args.repr_c || args.stable_abi.is_some();
+        if repr_c && (args.downcast || args.partial_eq || args.eq || args.hash || args.query) {
+            return Err(Error::new_spanned(
+                params,
+                "`repr_c` and `stable_abi` can't be combined with other tinydyn options",
+            ));
+        }
+        Ok(args)
+    }
+}
+
+/// Removes the `#[tinydyn(..)]` attributes of each method, returning their options in order.
+fn take_method_attrs(trait_item: &mut ItemTrait) -> Result<Vec<MethodArgs>> {
+    let mut methods = Vec::new();
+    for item in &mut trait_item.items {
+        let TraitItem::Fn(fn_item) = item else {
+            continue;
+        };
+        let mut method_args = MethodArgs::default();
+        let mut result = Ok(());
+        fn_item.attrs.retain(|attr| {
+            if !attr.path().is_ident("tinydyn") || result.is_err() {
+                return true;
+            }
+            result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("since") {
+                    if method_args.since.is_some() {
+                        return Err(meta.error("duplicate tinydyn method option"));
+                    }
+                    method_args.since = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("optional") {
+                    if method_args.optional.is_some() {
+                        return Err(meta.error("duplicate tinydyn method option"));
+                    }
+                    method_args.optional = Some(meta.path.span());
+                } else {
+                    return Err(meta.error("unknown tinydyn method option"));
+                }
+                Ok(())
+            });
+            false
+        });
+        result?;
+        methods.push(method_args);
+    }
+    Ok(methods)
+}
+
 // TODO: refactor to properly separate out parsing logic and token generation logic.
 struct CommonNames {
     tinydyn: Ident,
@@ -84,6 +277,37 @@ impl CommonNames {
             concrete,
         }
     }
+
+    /// Declares an extension trait with `methods`, implemented for the tinydyn trait object.
+    fn helper_trait(
+        &self,
+        ident: Ident,
+        doc: &str,
+        decls: &[TokenStream],
+        methods: &[TokenStream],
+    ) -> (Ident, TokenStream) {
+        let Self {
+            tinydyn,
+            private,
+            trait_object,
+            ..
+        } = self;
+        let tokens = quote!(
+            #[doc = #doc]
+            pub trait #ident {
+                #(#decls)*
+            }
+
+            impl<Trait> #ident for #private ::DynTarget<Trait>
+            where
+                Trait: ?Sized + #tinydyn ::DynTrait,
+                Trait::Plain: #private ::HasPart<#trait_object>,
+            {
+                #(#methods)*
+            }
+        );
+        (ident, tokens)
+    }
 }
 
 #[derive(Clone)]
@@ -176,7 +400,7 @@ impl<'a> MethodArgInfo<'a> {
                     receiver: Some(receiver_arg),
                     colon: self_arg.colon_token,
                     needs_bare_transmute: BareConversionNeeded(false),
-                    orig_arg_type: &*self_arg.ty,
+                    orig_arg_type: &self_arg.ty,
                     bare_arg_type: Box::new(
                         syn::parse2(quote!(#private ::SelfPtr<#pointer_to #trait_object>)).unwrap(),
                     ),
@@ -185,7 +409,7 @@ impl<'a> MethodArgInfo<'a> {
             }
             syn::FnArg::Typed(pat_type) => {
                 let orig_arg_type = &pat_type.ty;
-                let (bare_arg_type, needs_bare_transmute) = to_bare_arg_type(&orig_arg_type)?;
+                let (bare_arg_type, needs_bare_transmute) = to_bare_arg_type(orig_arg_type)?;
                 MethodArgInfo {
                     arg_ident: Ident::new(&format!("arg{arg_num}"), Span::mixed_site()),
                     receiver: None,
@@ -214,12 +438,70 @@ impl<'a> MethodArgInfo<'a> {
 
 struct BareConversionNeeded(pub bool);
 
+/// A field in the generated vtable.
+struct VtableEntry {
+    ident: Ident,
+    /// The type of the field, usually a function pointer.
+    ty: TokenStream,
+    /// Initializes the field for the type `Concrete`.
+    builder: TokenStream,
+    /// For trait methods, the type of function a vtable builder accepts for this entry.
+    setter_ty: Option<TokenStream>,
+    /// Whether this is an `Option` that's `None` unless the concrete type overrides the method,
+    /// or is marked with the auto traits it requires.
+    optional: bool,
+    /// Compares this entry of `self` and `other`, if it's not a function pointer.
+    eq: Option<TokenStream>,
+    /// Initializes the field for a std trait object, with `Concrete` a `&'static dyn Trait` or
+    /// `&'static mut dyn Trait`, or `None` if it can't be.
+    std_builder: Option<TokenStream>,
+}
+
+impl VtableEntry {
+    fn new(ident: Ident, ty: impl ToTokens, builder: TokenStream) -> Self {
+        Self {
+            ident,
+            ty: ty.into_token_stream(),
+            builder,
+            setter_ty: None,
+            optional: false,
+            eq: None,
+            std_builder: None,
+        }
+    }
+}
+
+/// The vtable entry of a `where Self: Sync` method, filled in by the marked vtables of
+/// `dyn Trait + Sync`.
+struct MarkedEntry {
+    ident: Ident,
+    /// The auto traits the method requires of `Self`.
+    self_bounds: Vec<syn::Path>,
+    /// Initializes the entry for the type `Concrete`, once it's known to meet `self_bounds`.
+    builder: TokenStream,
+}
+
 struct TraitMethod<'a> {
     sig: &'a syn::Signature,
     args: Vec<MethodArgInfo<'a>>,
     bare_output: syn::ReturnType,
     output_needs_transmute: BareConversionNeeded,
     receiver: ReceiverArg<'a>,
+    /// The auto traits `Self` must implement to call the method, like `where Self: Sync`.
+    self_bounds: Vec<syn::Path>,
+}
+
+/// Whether `pred` only bounds `Self` by auto traits, like `Self: Send + Sync`.
+fn is_self_auto_bound(pred: &syn::PredicateType) -> bool {
+    let is_self = matches!(&pred.bounded_ty, syn::Type::Path(ty) if ty.qself.is_none() && ty.path.is_ident("Self"));
+    is_self
+        && pred.lifetimes.is_none()
+        && pred.bounds.iter().all(|bound| {
+            matches!(bound, TypeParamBound::Trait(bound)
+                if matches!(bound.modifier, syn::TraitBoundModifier::None)
+                    && bound.lifetimes.is_none()
+                    && markers::is_auto_trait(&bound.path))
+        })
 }
 
 impl<'a> TraitMethod<'a> {
@@ -234,13 +516,23 @@ impl<'a> TraitMethod<'a> {
             }
         }
 
+        let mut self_bounds = Vec::new();
         if let Some(where_clause) = &generics.where_clause {
             for predicate in &where_clause.predicates {
-                if !matches!(predicate, syn::WherePredicate::Lifetime(_)) {
-                    return Err(unimplemented(
-                        where_clause,
-                        "non-lifetime method where clause",
-                    ));
+                match predicate {
+                    syn::WherePredicate::Lifetime(_) => {}
+                    syn::WherePredicate::Type(pred) if is_self_auto_bound(pred) => {
+                        self_bounds.extend(pred.bounds.iter().map(|bound| match bound {
+                            TypeParamBound::Trait(bound) => bound.path.clone(),
+                            _ => unreachable!(),
+                        }));
+                    }
+                    _ => {
+                        return Err(unimplemented(
+                            predicate,
+                            "method where clauses other than lifetimes and `Self: Send`",
+                        ))
+                    }
                 }
             }
         }
@@ -264,11 +556,8 @@ impl<'a> TraitMethod<'a> {
         let (bare_output, output_needs_transmute) = match &sig.output {
             syn::ReturnType::Default => (syn::ReturnType::Default, BareConversionNeeded(false)),
             syn::ReturnType::Type(arrow, ty) => {
-                let (bare_arg_type, need_convert) = to_bare_arg_type(&*ty)?;
-                (
-                    syn::ReturnType::Type(arrow.clone(), bare_arg_type),
-                    need_convert,
-                )
+                let (bare_arg_type, need_convert) = to_bare_arg_type(ty)?;
+                (syn::ReturnType::Type(*arrow, bare_arg_type), need_convert)
             }
         };
         Ok(Self {
@@ -277,6 +566,7 @@ impl<'a> TraitMethod<'a> {
             args,
             bare_output,
             output_needs_transmute,
+            self_bounds,
         })
     }
 
@@ -291,22 +581,65 @@ impl<'a> TraitMethod<'a> {
 /// All of the data necessary to build the module that impls for `tinydyn`.
 struct TinydynImplModule {
     names: CommonNames,
+    /// The visibility of the trait, which the vtable type is re-exported with.
+    vis: syn::Visibility,
+    /// Whether the vtable is `repr(C)`, without Rust-only entries like the type name.
+    repr_c: bool,
+    /// The vtable entry naming the concrete type. `repr_c` vtables leave it out, as C can't
+    /// construct it, and so do inline vtables, to stay the size of one function pointer.
+    type_name: Option<Ident>,
+    /// The C header for a `repr_c` vtable.
+    c_header: Option<String>,
+    /// The marker traits the trait object can carry.
+    markers: Markers,
+    /// The auto trait and lifetime supertraits of the trait, like `Send + 'static`.
+    supertraits: Punctuated<TypeParamBound, Token![+]>,
+    /// The vtable entries of `where Self: Sync` methods, filled in by marked vtables.
+    marked_entries: Vec<MarkedEntry>,
+    /// Formats `self_` with `f` using the `Debug` entry, or is `None` without one.
+    fmt_debug: TokenStream,
+    /// Formats `self_` with `f` using the `Display` entry, or is `None` without one.
+    fmt_display: TokenStream,
+    /// Builds the `AbiHeader` at the start of a `stable_abi` vtable.
+    abi_header: Option<TokenStream>,
+    /// Helper items exported next to the vtable, like the `{Trait}Versioned` extension trait and
+    /// its impl, or the `{Trait}CVtable` struct.
+    helper_traits: Vec<(Ident, TokenStream)>,
+    /// The `{Trait}Std` adapter and the `StdDyn` impl, if the trait can convert to and from
+    /// std trait objects.
+    std_dyn: Option<(Ident, TokenStream)>,
     // trait_ident: Ident,
 
     // trait_object: TokenStream,
     // private: TokenStream,
-    // vtable_build_expr: TokenStream,
-    vtable_entries: Vec<TokenStream>,
+    vtable_entries: Vec<VtableEntry>,
     vtable_callers: Vec<TokenStream>,
+    /// The methods of the trait impls on `Ref` and `RefMut`.
+    handle_methods: Vec<TokenStream>,
+    /// Whether a method takes `&mut self`, so `Ref` can't implement the trait.
+    has_mut_method: bool,
+    /// Whether `Ref` and `RefMut` implement the trait, enabled by `ref_impls`.
+    ref_impls: bool,
+    /// Extra bounds every concrete type must meet, beyond implementing the trait.
+    concrete_bounds: Vec<TokenStream>,
+    /// Impls of optional tinydyn traits, like `DynPartialEq`, for the trait object,
+    /// and `extern "C"` thunks for `repr_c`.
+    extra_impls: Vec<TokenStream>,
+    /// This builds the full vtable for a given `Concrete`.
+    vtable_build_expr: TokenStream,
     /// This is statically alloc'd for every (trait, concrete).
     static_vtable_type: TokenStream,
     /// This builds the `static_vtable_type` for this (trait, concrete).
     static_vtable_expr: TokenStream,
     /// This extra data is carried along in DynPtr.
     metadata_type: TokenStream,
-    /// When building a wide pointer, this gets the metadata.
+    /// Converts a `vtable: &'static Vtable` into the metadata.
+    metadata_from_vtable: TokenStream,
+    /// The metadata for a given `Concrete`, used when building a wide pointer.
     /// This might build a vtable or get a static one.
     metadata_getter: TokenStream,
+    /// Gets a pointer identifying the vtable from `meta`, for identity comparisons.
+    vtable_addr: TokenStream,
 }
 
 impl ToTokens for TinydynImplModule {
@@ -321,10 +654,30 @@ impl ToTokens for TinydynImplModule {
         let Self {
             static_vtable_type,
             static_vtable_expr,
+            vtable_build_expr,
             metadata_type,
+            metadata_from_vtable,
             metadata_getter,
+            vtable_addr,
             vtable_callers,
             vtable_entries,
+            handle_methods,
+            has_mut_method,
+            ref_impls,
+            concrete_bounds,
+            extra_impls,
+            markers,
+            supertraits,
+            marked_entries,
+            fmt_debug,
+            fmt_display,
+            vis,
+            repr_c,
+            type_name,
+            c_header,
+            abi_header,
+            helper_traits,
+            std_dyn,
             names:
                 CommonNames {
                     vtable_ident,
@@ -333,20 +686,231 @@ impl ToTokens for TinydynImplModule {
                     tinydyn,
                     private,
                     concrete,
-                    ..
+                    self_local,
+                    meta_local,
                 },
             ..
         } = self;
 
         let mod_ident = format_ident!("__tinydyn_impl_{trait_ident}");
+        let entry_idents = vtable_entries.iter().map(|entry| &entry.ident);
+        let entry_types = vtable_entries.iter().map(|entry| &entry.ty);
+        let entry_eqs = vtable_entries.iter().map(|entry| {
+            let VtableEntry {
+                ident,
+                optional,
+                eq,
+                ..
+            } = entry;
+            if let Some(eq) = eq {
+                eq.clone()
+            } else if *optional {
+                quote!(self.#ident.map(|f| f as *const ()) == other.#ident.map(|f| f as *const ()))
+            } else {
+                quote!(self.#ident as *const () == other.#ident as *const ())
+            }
+        });
         let newtype_ident = format_ident!("{trait_ident}Newtype");
+        let marker_impls = markers.impls(
+            &trait_object,
+            &newtype_ident,
+            |_| quote!(#concrete: #trait_ident #(+ #concrete_bounds)*,),
+            |object, markers| {
+                let (idents, builders): (Vec<_>, Vec<_>) = marked_entries
+                    .iter()
+                    .filter(|entry| {
+                        entry.self_bounds.iter().all(|bound| {
+                            markers
+                                .iter()
+                                .any(|marker| markers::same_trait(marker, bound))
+                        })
+                    })
+                    .map(|entry| (&entry.ident, &entry.builder))
+                    .unzip();
+                if idents.is_empty() {
+                    return TokenStream::new();
+                }
+                quote!(
+                    const MARKED_VTABLE_REF: &'static #vtable_ident = &#vtable_ident {
+                        #(#idents: Some(unsafe { #builders }),)*
+                        ..<Self as #tinydyn ::BuildDynMeta<#trait_object>>::VTABLE
+                    };
+                    const MARKED_METADATA: &'static #vtable_ident =
+                        <Self as #tinydyn ::Implements<#object>>::MARKED_VTABLE_REF;
+                )
+            },
+        );
+
+        let vtable_doc = format!(" The tinydyn vtable for `dyn {trait_ident}`.");
+        let ref_mut_impl = ref_impls.then(|| {
+            quote!(
+                impl<Trait> #trait_ident for #tinydyn ::RefMut<'_, Trait>
+                where
+                    Trait: ?Sized + #tinydyn ::DynTrait,
+                    Trait::Plain: #private ::HasPart<#trait_object>,
+                    Self: #supertraits,
+                {
+                    #(#handle_methods)*
+                }
+            )
+        });
+        // A `Ref` can only implement a trait of `&self` methods.
+        let ref_impl = (ref_impls && !has_mut_method).then(|| {
+            quote!(
+                impl<Trait> #trait_ident for #tinydyn ::Ref<'_, Trait>
+                where
+                    Trait: ?Sized + #tinydyn ::DynTrait,
+                    Trait::Plain: #private ::HasPart<#trait_object>,
+                    Self: #supertraits,
+                {
+                    #(#handle_methods)*
+                }
+            )
+        });
+
+        let builder_ident = format_ident!("{vtable_ident}Builder");
+        let builder_doc = format!(
+            " Assembles a [`{vtable_ident}`] from functions that take a `&Concrete` or \
+            `&mut Concrete` as `self`.\n\n Closures passed to the setters can't capture \
+            anything. Keep their state in the `Concrete` instead."
+        );
+        let (method_entries, extra_entries): (Vec<_>, Vec<_>) = vtable_entries
+            .iter()
+            .partition(|entry| entry.setter_ty.is_some());
+        let method_idents: Vec<_> = method_entries.iter().map(|entry| &entry.ident).collect();
+        let method_types = method_entries.iter().map(|entry| &entry.ty);
+        // An optional method may be left unset.
+        let method_defaults = method_entries.iter().map(|entry| {
+            entry
+                .optional
+                .then(|| quote!(Some(None)))
+                .unwrap_or(quote!(None))
+        });
+        let setter_types = method_entries.iter().map(|entry| &entry.setter_ty);
+        let setter_idents = method_idents.iter().map(|&ident| {
+            // Don't collide with `build`.
+            if ident == "build" {
+                format_ident!("set_{ident}")
+            } else {
+                ident.clone()
+            }
+        });
+        let setter_docs = method_idents
+            .iter()
+            .map(|ident| format!(" Sets the function called by `{trait_ident}::{ident}`."));
+        let extra_idents = extra_entries.iter().map(|entry| &entry.ident);
+        let extra_builders = extra_entries.iter().map(|entry| &entry.builder);
+
+        let type_name_getter = match &type_name {
+            Some(type_name) => quote!(meta.#type_name.get()),
+            None => quote!({
+                let _ = meta;
+                None
+            }),
+        };
+        let type_name = type_name.as_slice();
+        let repr = repr_c.then(|| quote!(#[repr(C)]));
+        let abi_header = abi_header.as_slice();
+        let header = abi_header
+            .iter()
+            .map(|_| format_ident!("__tinydyn_header"))
+            .collect::<Vec<_>>();
+        let (helper_idents, helper_traits): (Vec<_>, Vec<_>) =
+            helper_traits.into_iter().chain(std_dyn).unzip();
+        let c_header = c_header.map(|c_header| {
+            let doc = format!(" A C header declaring `{vtable_ident}`, `{trait_ident}Ref` and `{trait_ident}RefMut`.");
+            quote!(
+                impl #vtable_ident {
+                    #[doc = #doc]
+                    pub const C_HEADER: &'static str = #c_header;
+                }
+            )
+        });
 
-        quote!(mod #mod_ident {
+        quote!(
+        #vis use #mod_ident::{#vtable_ident, #builder_ident #(, #helper_idents)*};
+
+        mod #mod_ident {
             use super::*;
 
-            #[derive(Copy, Clone)]
+            #[doc = #vtable_doc]
+            #[derive(Copy, Clone, Debug)]
+            #repr
             pub struct #vtable_ident {
-                #(#vtable_entries,)*
+                #(#header: #tinydyn ::AbiHeader,)*
+                #(#entry_idents: #entry_types,)*
+                #(#type_name: #private ::TypeName,)*
+            }
+
+            #c_header
+
+            // Compares function addresses, which may be duplicated or merged by the compiler.
+            impl PartialEq for #vtable_ident {
+                fn eq(&self, other: &Self) -> bool {
+                    #(self.#header == other.#header &&)*
+                    #(#entry_eqs &&)*
+                    #(self.#type_name == other.#type_name &&)*
+                    true
+                }
+            }
+
+            impl Eq for #vtable_ident {}
+
+            impl #vtable_ident {
+                /// Starts building a vtable whose methods are called on a `Concrete`.
+                pub const fn builder<Concrete>() -> #builder_ident <Concrete> {
+                    #builder_ident {
+                        #(#method_idents: #method_defaults,)*
+                        __tinydyn_concrete: core::marker::PhantomData,
+                    }
+                }
+
+                /// Starts building a vtable from an existing one, to override some of its methods.
+                pub const fn builder_from<Concrete>(
+                    vtable: &#tinydyn ::VTableFor<#trait_object, Concrete>,
+                ) -> #builder_ident <Concrete> {
+                    let vtable = vtable.get();
+                    #builder_ident {
+                        #(#method_idents: Some(vtable.#method_idents),)*
+                        __tinydyn_concrete: core::marker::PhantomData,
+                    }
+                }
+            }
+
+            #[doc = #builder_doc]
+            pub struct #builder_ident <Concrete> {
+                #(#method_idents: Option<#method_types>,)*
+                __tinydyn_concrete: core::marker::PhantomData<fn(Concrete)>,
+            }
+
+            impl<Concrete> #builder_ident <Concrete> {
+                #(
+                    #[doc = #setter_docs]
+                    pub const fn #setter_idents(mut self, f: #setter_types) -> Self {
+                        // SAFETY: `f` is only called with `self` pointing to a `Concrete`, and
+                        // lifetimes don't change the ABI of a function pointer.
+                        self.#method_idents = Some(unsafe { core::mem::transmute(f) });
+                        self
+                    }
+                )*
+            }
+
+            impl<Concrete> #builder_ident <Concrete>
+            where
+                #(#concrete: #concrete_bounds,)*
+            {
+                /// Builds the vtable, or returns `None` if a method wasn't set.
+                pub const fn build(self) -> Option<#tinydyn ::VTableFor<#trait_object, Concrete>> {
+                    #(let Some(#method_idents) = self.#method_idents else { return None; };)*
+                    Some(unsafe {
+                        #tinydyn ::VTableFor::new_unchecked(#vtable_ident {
+                            #(#header: #abi_header,)*
+                            #(#method_idents,)*
+                            #(#extra_idents: #extra_builders,)*
+                            #(#type_name: #private ::TypeName::of::<#concrete>(),)*
+                        })
+                    })
+                }
             }
 
             #[repr(transparent)]
@@ -355,65 +919,79 @@ impl ToTokens for TinydynImplModule {
             unsafe impl #tinydyn ::PlainDyn for #trait_object {
                 type Metadata = #metadata_type;
                 type StaticVTable = #static_vtable_type;
+                type VTable = #vtable_ident;
                 type LocalNewtype<T> = #newtype_ident <T>;
-            }
 
-            unsafe impl #tinydyn ::DynTrait for #trait_object {
-                type Plain = #trait_object;
-                type RemoveSend = #trait_object;
-                type RemoveSync = #trait_object;
-            }
+                #[inline(always)]
+                fn metadata_from_vtable(vtable: &'static #vtable_ident) -> #metadata_type {
+                    #metadata_from_vtable
+                }
 
-            unsafe impl #tinydyn ::DynTrait for #trait_object + Send {
-                type Plain = #trait_object;
-                type RemoveSend = #trait_object;
-                type RemoveSync = #trait_object + Send;
-            }
+                #[inline(always)]
+                fn vtable_addr(meta: #metadata_type) -> *const () {
+                    #vtable_addr
+                }
 
-            unsafe impl #tinydyn ::DynTrait for #trait_object + Sync {
-                type Plain = #trait_object;
-                type RemoveSend = #trait_object + Sync;
-                type RemoveSync = #trait_object;
-            }
+                #[inline(always)]
+                fn type_name(meta: #metadata_type) -> Option<&'static str> {
+                    #type_name_getter
+                }
+
+                #[inline(always)]
+                unsafe fn fmt_debug(
+                    #meta_local: #metadata_type,
+                    #self_local: #private ::SelfPtr<*const #trait_object>,
+                    f: &mut core::fmt::Formatter<'_>,
+                ) -> Option<core::fmt::Result> {
+                    #fmt_debug
+                }
 
-            unsafe impl #tinydyn ::DynTrait for #trait_object + Send + Sync {
-                type Plain = #trait_object;
-                type RemoveSend = #trait_object + Sync;
-                type RemoveSync = #trait_object + Send;
+                #[inline(always)]
+                unsafe fn fmt_display(
+                    #meta_local: #metadata_type,
+                    #self_local: #private ::SelfPtr<*const #trait_object>,
+                    f: &mut core::fmt::Formatter<'_>,
+                ) -> Option<core::fmt::Result> {
+                    #fmt_display
+                }
             }
 
             unsafe impl<#concrete> #tinydyn ::BuildDynMeta<#trait_object> for #newtype_ident <#concrete>
             where
-                #concrete: #trait_ident,
+                #concrete: #trait_ident #(+ #concrete_bounds)*,
             {
+                const VTABLE: #vtable_ident = #vtable_build_expr;
+                const VTABLE_REF: &'static #vtable_ident = &Self::VTABLE;
                 const STATIC_VTABLE: #static_vtable_type = #static_vtable_expr;
-
-                fn metadata() -> #metadata_type {
-                    #metadata_getter
-                }
+                const METADATA: #metadata_type = #metadata_getter;
             }
-            unsafe impl<T> #tinydyn ::Implements<#trait_object> for #newtype_ident <T> where T: #trait_ident {}
-            unsafe impl<T> #tinydyn ::Implements<#trait_object + Send> for #newtype_ident <T> where T: #trait_ident + Send {}
-            unsafe impl<T> #tinydyn ::Implements<#trait_object + Sync> for #newtype_ident <T> where T: #trait_ident + Sync {}
-            unsafe impl<T> #tinydyn ::Implements<#trait_object + Send + Sync> for #newtype_ident <T> where T: #trait_ident + Send + Sync {}
+
+            #marker_impls
+
+            #(#extra_impls)*
+
+            #(#helper_traits)*
 
             impl<Trait> #trait_ident for #private ::DynTarget<Trait>
             where
-                Trait: ?Sized + #tinydyn ::DynTrait<Plain = #trait_object>,
+                Trait: ?Sized + #tinydyn ::DynTrait,
+                Trait::Plain: #private ::HasPart<#trait_object>,
+                #private ::DynTarget<Trait>: #supertraits,
             {
                 #(#vtable_callers)*
             }
-        })
-    }
 
-    fn to_token_stream(&self) -> TokenStream {
-        self.clone().into_token_stream()
+            #ref_impl
+
+            #ref_mut_impl
+        })
     }
 }
 
 impl TinydynImplModule {
-    fn new(trait_item: ItemTrait) -> Result<Self> {
+    fn new(trait_item: ItemTrait, args: TraitArgs) -> Result<Self> {
         let ItemTrait {
+            vis,
             generics,
             ident: trait_ident,
             supertraits,
@@ -422,14 +1000,16 @@ impl TinydynImplModule {
             ..
         } = trait_item;
         generics_unimplemented(&generics)?;
-        supertraits_unimplemented(&supertraits)?;
+        let fmt_traits = supertraits_unimplemented(&supertraits)?;
         unsafe_trait_unsupported(&unsafety)?;
 
         let names = CommonNames::new(trait_ident);
         let CommonNames {
             self_local,
+            tinydyn,
             private,
             trait_ident,
+            trait_object,
             vtable_ident,
             concrete,
             meta_local,
@@ -443,32 +1023,93 @@ impl TinydynImplModule {
                 _ => Err(unimplemented(&item, "non-function items")),
             })
             .collect::<Result<_>>()?;
+        let repr_c = args.repr_c || args.stable_abi.is_some();
+        let method_since = method_versions(&fn_items, &args)?;
+        for (fn_item, method_args) in fn_items.iter().zip(&args.methods) {
+            let Some(optional) = method_args.optional else {
+                continue;
+            };
+            if repr_c {
+                return Err(Error::new(
+                    optional,
+                    "`optional` methods can't be used with `repr_c` or `stable_abi`",
+                ));
+            }
+            if fn_item.default.is_none() {
+                return Err(Error::new(
+                    optional,
+                    "`optional` methods must have a default body to fall back to",
+                ));
+            }
+        }
+        let has_optional = args.methods.iter().any(|method| method.optional.is_some());
 
         // vtable:
-        // - entries: the function pointer fields in the vtable
-        // - builders: the field initializers for the concrete type's vtable
+        // - entries: the function pointer fields in the vtable and their initializers
         // - callers: the trait impl methods on DynTarget that call a trait method from vtable
-        let mut vtable_entries: Vec<TokenStream> = Vec::new();
-        let mut vtable_builders: Vec<TokenStream> = Vec::new();
+        let mut vtable_entries: Vec<VtableEntry> = Vec::new();
         let mut vtable_callers: Vec<TokenStream> = Vec::new();
+        let mut extra_impls: Vec<TokenStream> = Vec::new();
+        let mut c_methods = Vec::new();
+        let mut c_callbacks = Vec::new();
+        let mut c_vtable_fields: Vec<TokenStream> = Vec::new();
+        let mut versioned_decls: Vec<TokenStream> = Vec::new();
+        let mut versioned_methods: Vec<TokenStream> = Vec::new();
+        let mut optional_decls: Vec<TokenStream> = Vec::new();
+        let mut optional_methods: Vec<TokenStream> = Vec::new();
+        let mut marked_entries: Vec<MarkedEntry> = Vec::new();
+        // std trait objects: thunks calling through a `&dyn Trait` or `&mut dyn Trait`, and the
+        // methods of the `{Trait}Std` adapter
+        let mut std_thunks: Vec<TokenStream> = Vec::new();
+        let mut adapter_methods: Vec<TokenStream> = Vec::new();
+        // The methods of the impls on `Ref` and `RefMut`, which only `RefMut` has if any take
+        // `&mut self`.
+        let mut handle_methods: Vec<TokenStream> = Vec::new();
+        let mut has_mut_method = false;
+        let mut last_required = None;
+        let entry_local = Ident::new("entry", Span::mixed_site());
+        let entry_abi: Option<syn::Abi> = repr_c.then(|| syn::parse_quote!(extern "C"));
         let methods: Vec<TraitMethod> = fn_items
             .iter()
             .map(|fn_item| TraitMethod::new(&fn_item.sig, &names))
             .collect::<Result<_>>()?;
-        for mut method in methods {
+        let method_info = fn_items.iter().zip(&args.methods).zip(method_since);
+        for (mut method, ((fn_item, method_args), since)) in methods.into_iter().zip(method_info) {
             let sig = method.sig;
             let entry_ident = sig.ident.clone();
-            vtable_builders.push(quote!(
-                #entry_ident: core::mem::transmute(
-                    <#concrete as #trait_ident>:: #entry_ident as *const ())
-            ));
+            let self_bounds = core::mem::take(&mut method.self_bounds);
+            if let Some(bound) = self_bounds.first() {
+                if repr_c {
+                    return Err(Error::new_spanned(
+                        bound,
+                        "`where Self: ..` methods can't be used with `repr_c` or `stable_abi`",
+                    ));
+                }
+                if let Some(optional) = method_args.optional {
+                    return Err(Error::new(
+                        optional,
+                        "`optional` methods can't have `where Self: ..` bounds",
+                    ));
+                }
+            }
+            if let Some(bound) = self_bounds
+                .iter()
+                .find(|bound| !args.markers.has_auto(bound))
+            {
+                return Err(Error::new_spanned(
+                    bound,
+                    "add this to the trait's `markers(..)` to require it of `Self`",
+                ));
+            }
             let erased_cons = match method.receiver.type_ {
-                ReceiverType::SharedRef => quote!(self_ref),
-                ReceiverType::MutableRef => quote!(self_mut),
+                ReceiverType::SharedRef => quote!(part_ref::<#trait_object>),
+                ReceiverType::MutableRef => quote!(part_mut::<#trait_object>),
             };
             let mut impl_sig = sig.clone();
             let mut call_args = Vec::new();
             let mut args_to_bare = Vec::new();
+            // Binds the original argument patterns, for running the default body.
+            let mut bind_orig_args = Vec::new();
             for (mut pair, arg) in impl_sig.inputs.pairs_mut().zip(&method.args) {
                 // Replace with our custom argument name
                 let &MethodArgInfo {
@@ -478,21 +1119,24 @@ impl TinydynImplModule {
                     ..
                 } = arg;
                 if let syn::FnArg::Typed(pat_type) = pair.value_mut() {
-                    pat_type.pat = Box::new(syn::Pat::Ident(syn::PatIdent {
+                    let orig_pat = &pat_type.pat;
+                    bind_orig_args.push(quote!(let #orig_pat = #arg_ident;));
+                    *pat_type.pat = syn::Pat::Ident(syn::PatIdent {
                         attrs: Vec::new(),
                         by_ref: None,
                         mutability: None,
                         ident: arg_ident.clone(),
                         subpat: None,
-                    }));
+                    });
                 }
 
                 // Erase lifetimes and prepare for the bare fn (pointer)
-                // `transmute` doesn't work with generic arguments, but `transmute_copy` does.
+                // `transmute` doesn't work with generic arguments, so this checks their layouts in a
+                // `const` block instead.
                 if arg.needs_bare_transmute.0 {
                     args_to_bare.push(quote!(
                         let #arg_ident = #private
-                            ::runtime_layout_verified_transmute::<#orig_arg_type, #bare_arg_type>
+                            ::layout_verified_transmute::<#orig_arg_type, #bare_arg_type>
                             (#arg_ident);
                     ));
                 }
@@ -502,73 +1146,734 @@ impl TinydynImplModule {
 
             let bare_inputs: Punctuated<syn::BareFnArg, Token![,]> = method.drain_bare_inputs();
 
-            let mut vtable_call = quote!((#meta_local . #entry_ident)(#(#call_args,)*));
+            // How the caller gets the function pointer out of `meta`.
+            let mut entry_access = quote!(#meta_local . #entry_ident);
+            // For a versioned method, an `Option` of the function pointer.
+            let mut versioned_entry = None;
+            if let (Some(stable_abi), Some(since)) = (&args.stable_abi, since) {
+                // A stable vtable may be older and smaller than its type, so only access
+                // the entries it covers through a raw pointer.
+                entry_access = quote!((*#meta_local.as_ptr()).#entry_ident);
+                if since > stable_abi.min_version {
+                    versioned_entry = Some(quote!((
+                        if #meta_local.covers(
+                            core::mem::offset_of!(#vtable_ident, #entry_ident)
+                                + core::mem::size_of::<*const ()>(),
+                        ) {
+                            Some(#entry_access)
+                        } else {
+                            None
+                        }
+                    )));
+                    entry_access = entry_local.to_token_stream();
+                } else {
+                    last_required = Some(entry_ident.clone());
+                }
+            }
+            // For an optional method, runs the default body if the entry is missing.
+            let mut fallback = None;
+            if method_args.optional.is_some() {
+                let default_body = &fn_item.default;
+                fallback = Some(quote!(
+                    let Some(#entry_local) = #meta_local.#entry_ident else {
+                        #(#bind_orig_args)*
+                        return #default_body;
+                    };
+                ));
+                entry_access = entry_local.to_token_stream();
+
+                let has_ident = format_ident!("has_{entry_ident}");
+                let doc = format!(
+                    " Whether the concrete type overrides `{trait_ident}::{entry_ident}`, \
+                    rather than using its default body."
+                );
+                optional_decls.push(quote!(
+                    #[doc = #doc]
+                    fn #has_ident(&self) -> bool;
+                ));
+                optional_methods.push(quote!(
+                    #[inline(always)]
+                    fn #has_ident(&self) -> bool {
+                        #private ::DynTarget::part_meta::<#trait_object>(self).#entry_ident.is_some()
+                    }
+                ));
+            }
+            // For a `where Self: Sync` method, the entry is only filled in for a marked vtable.
+            // SAFETY: The method is only callable if the trait object has the markers, and the
+            // metadata of a trait object is always built for its markers. `Implements` fills in
+            // the entry, and `from_raw_parts` requires it.
+            let mut get_entry = None;
+            if !self_bounds.is_empty() {
+                get_entry = Some(quote!(
+                    let #entry_local = #meta_local.#entry_ident.unwrap_unchecked();
+                ));
+                entry_access = entry_local.to_token_stream();
+            }
+            let mut vtable_call = quote!((#entry_access)(#(#call_args,)*));
             // don't forget to transmute the output type if it needs it
             if let (syn::ReturnType::Type(_, out_ty), syn::ReturnType::Type(_, bare_ty)) =
                 (&sig.output, &method.bare_output)
             {
                 if method.output_needs_transmute.0 {
-                    let out_ty = &*out_ty;
-                    vtable_call = quote!(#private ::runtime_layout_verified_transmute::<#bare_ty, #out_ty>(
+                    vtable_call = quote!(#private ::layout_verified_transmute::<#bare_ty, #out_ty>(
                             #vtable_call));
                 }
             }
 
             let fn_pointer = syn::TypeBareFn {
                 lifetimes: None,
-                unsafety: sig.unsafety.clone(),
-                abi: sig.abi.clone(),
-                fn_token: sig.fn_token.clone(),
-                paren_token: sig.paren_token.clone(),
+                unsafety: sig.unsafety,
+                abi: entry_abi.clone().or_else(|| sig.abi.clone()),
+                fn_token: sig.fn_token,
+                paren_token: sig.paren_token,
                 inputs: bare_inputs,
                 variadic: None,
                 output: method.bare_output,
             };
-            vtable_entries.push(quote!(#entry_ident: #fn_pointer));
-            vtable_callers.push(quote!(
-                #[inline(always)]
-                #impl_sig {
-                    let #meta_local = #private ::DynTarget::meta(self);
-                    let #self_local = #private ::DynTarget:: #erased_cons (self);
-                    unsafe {
-                        #(#args_to_bare)*
-                        #vtable_call
+            let concrete_sig = ConcreteSig::new(sig, concrete);
+            let std_thunk = format_ident!("__tinydyn_std_{entry_ident}");
+            {
+                // Calls the method through the std vtable of the `&dyn Trait` that `self` points to.
+                let ConcreteSig {
+                    lifetimes,
+                    inputs,
+                    output,
+                } = &concrete_sig;
+                let arg_idents: Vec<_> = (0..inputs.len())
+                    .map(|arg_num| Ident::new(&format!("arg{arg_num}"), Span::mixed_site()))
+                    .collect();
+                let (self_arg, other_args) = arg_idents.split_first().unwrap();
+                // Borrows the trait object that `self_arg`, a reference to a `StdPtr`, points to.
+                let (get_std_self, adapter_self) = match method.receiver.type_ {
+                    ReceiverType::SharedRef => (quote!(&**#self_arg), quote!(&*self.0)),
+                    // SAFETY: Only the vtable of `RefMut::from_std_dyn`, with a `&mut` pointer,
+                    // is used to call `&mut self` methods.
+                    ReceiverType::MutableRef => (
+                        quote!(unsafe { #private ::StdPtr::get_mut(#self_arg) }),
+                        quote!(&mut *self.0),
+                    ),
+                };
+                // The first call argument is `self`.
+                let other_call_args = &call_args[1..];
+                let unsafety = &sig.unsafety;
+                let where_clause = &sig.generics.where_clause;
+                std_thunks.push(quote!(
+                    #unsafety fn #std_thunk<
+                        #(#lifetimes,)*
+                        #concrete: #private ::StdPtr<Target = #trait_object>,
+                    >(
+                        #(#arg_idents: #inputs),*
+                    ) #output #where_clause {
+                        let #self_arg = #get_std_self;
+                        #unsafety {
+                            <#trait_object as #trait_ident>:: #entry_ident(#self_arg, #(#other_args),*)
+                        }
+                    }
+                ));
+                adapter_methods.push(quote!(
+                    #[inline(always)]
+                    #impl_sig {
+                        #unsafety {
+                            <P::Target as #trait_ident>:: #entry_ident(#adapter_self, #(#other_call_args),*)
+                        }
+                    }
+                ));
+            }
+            let std_builder = quote!(core::mem::transmute(#std_thunk::<#concrete> as *const ()));
+            let mut entry_fn = quote!(<#concrete as #trait_ident>:: #entry_ident);
+            if repr_c {
+                // Calls the trait method with the C calling convention.
+                c_methods.push(CMethod::new(sig)?);
+                let thunk_ident = format_ident!("__tinydyn_thunk_{entry_ident}");
+                let ConcreteSig {
+                    lifetimes,
+                    inputs,
+                    output,
+                } = &concrete_sig;
+                let arg_idents: Vec<_> = (0..inputs.len())
+                    .map(|arg_num| Ident::new(&format!("arg{arg_num}"), Span::mixed_site()))
+                    .collect();
+                let unsafety = &sig.unsafety;
+                let where_clause = &sig.generics.where_clause;
+                extra_impls.push(quote!(
+                    #unsafety extern "C" fn #thunk_ident<#(#lifetimes,)* #concrete: #trait_ident>(
+                        #(#arg_idents: #inputs),*
+                    ) #output #where_clause {
+                        #unsafety { <#concrete as #trait_ident>:: #entry_ident(#(#arg_idents),*) }
+                    }
+                ));
+                entry_fn = quote!(#thunk_ident::<#concrete>);
+
+                // The entry as C provides it, which may be null and takes raw pointers.
+                let mut c_entry_ty = fn_pointer.clone();
+                c_entry_ty.unsafety = Some(Default::default());
+                for (arg_num, arg) in c_entry_ty.inputs.iter_mut().enumerate() {
+                    arg.name = None;
+                    arg.ty = match (arg_num, &method.receiver.type_) {
+                        (0, ReceiverType::SharedRef) => {
+                            syn::parse_quote!(*const core::ffi::c_void)
+                        }
+                        (0, ReceiverType::MutableRef) => syn::parse_quote!(*mut core::ffi::c_void),
+                        _ => c_header::c_rust_type(&arg.ty),
+                    };
+                }
+                if let syn::ReturnType::Type(_, ty) = &mut c_entry_ty.output {
+                    **ty = c_header::c_rust_type(ty);
+                }
+                let doc = format!(" Called by `{trait_ident}::{entry_ident}`.");
+                c_vtable_fields.push(quote!(
+                    #[doc = #doc]
+                    pub #entry_ident: Option<#c_entry_ty>,
+                ));
+
+                if let ReceiverType::SharedRef = method.receiver.type_ {
+                    // The thunk as C sees it, taking a `const void *` for `self`.
+                    let mut callback_ty = fn_pointer.clone();
+                    callback_ty.unsafety = Some(Default::default());
+                    let self_arg = callback_ty.inputs.first_mut().unwrap();
+                    self_arg.name = None;
+                    self_arg.ty = syn::parse_quote!(*const core::ffi::c_void);
+                    c_callbacks.push((entry_ident.clone(), callback_ty));
+                }
+            }
+            let setter_ty =
+                Some(concrete_sig.fn_pointer(sig, &entry_abi.clone().or_else(|| sig.abi.clone())));
+            let entry_builder = quote!(core::mem::transmute(#entry_fn as *const ()));
+            vtable_entries.push(if method_args.optional.is_some() {
+                let method_name = entry_ident.to_string();
+                VtableEntry {
+                    setter_ty,
+                    optional: true,
+                    std_builder: Some(quote!(Some(#std_builder))),
+                    ..VtableEntry::new(
+                        entry_ident.clone(),
+                        quote!(Option<#fn_pointer>),
+                        quote!(
+                            if #private ::overrides::<#trait_object, #concrete>(#method_name) {
+                                Some(#entry_builder)
+                            } else {
+                                None
+                            }
+                        ),
+                    )
+                }
+            } else if !self_bounds.is_empty() {
+                marked_entries.push(MarkedEntry {
+                    ident: entry_ident.clone(),
+                    self_bounds,
+                    builder: entry_builder,
+                });
+                VtableEntry {
+                    setter_ty,
+                    optional: true,
+                    ..VtableEntry::new(
+                        entry_ident.clone(),
+                        quote!(Option<#fn_pointer>),
+                        quote!(None),
+                    )
+                }
+            } else {
+                VtableEntry {
+                    setter_ty,
+                    std_builder: Some(std_builder),
+                    ..VtableEntry::new(entry_ident.clone(), fn_pointer, entry_builder)
+                }
+            });
+            if let Some(versioned_entry) = &versioned_entry {
+                // Like an optional method, runs the default body if an older vtable is missing it.
+                let default_body = &fn_item.default;
+                fallback = Some(quote!(
+                    // SAFETY: The entry is only read if the header says the vtable holds it.
+                    let Some(#entry_local) = (unsafe { #versioned_entry }) else {
+                        #(#bind_orig_args)*
+                        return #default_body;
+                    };
+                ));
+
+                let mut try_sig = impl_sig.clone();
+                try_sig.ident = format_ident!("try_{entry_ident}");
+                try_sig.output = match &sig.output {
+                    syn::ReturnType::Default => syn::parse_quote!(-> Option<()>),
+                    syn::ReturnType::Type(arrow, ty) => syn::parse_quote!(#arrow Option<#ty>),
+                };
+                let doc = format!(
+                    " Calls `{trait_ident}::{entry_ident}`, or returns `None` if the vtable \
+                    is older than version {}.",
+                    since.unwrap(),
+                );
+                versioned_decls.push(quote!(
+                    #[doc = #doc]
+                    #try_sig;
+                ));
+                versioned_methods.push(quote!(
+                    #[inline(always)]
+                    #try_sig {
+                        let #meta_local = #private ::DynTarget::part_meta::<#trait_object>(self);
+                        let #self_local = #private ::DynTarget:: #erased_cons (self);
+                        unsafe {
+                            let #entry_local = #versioned_entry?;
+                            #(#args_to_bare)*
+                            Some(#vtable_call)
+                        }
+                    }
+                ));
+            }
+            // Calls the method through the vtable of `self`, using `DynTarget` or the `DynHandle`
+            // traits of `Ref` and `RefMut` to get the pointer.
+            let caller = |get_meta: TokenStream, get_self: TokenStream| {
+                quote!(
+                    #[inline(always)]
+                    #impl_sig {
+                        let #meta_local = #get_meta::part_meta::<#trait_object>(self);
+                        #fallback
+                        let #self_local = #get_self:: #erased_cons (self);
+                        unsafe {
+                            #get_entry
+                            #(#args_to_bare)*
+                            #vtable_call
+                        }
+                    }
+                )
+            };
+            let dyn_target = quote!(#private ::DynTarget);
+            vtable_callers.push(caller(dyn_target.clone(), dyn_target));
+            let handle = quote!(#private ::DynHandle);
+            handle_methods.push(caller(
+                handle.clone(),
+                match method.receiver.type_ {
+                    ReceiverType::SharedRef => handle,
+                    ReceiverType::MutableRef => {
+                        has_mut_method = true;
+                        quote!(#private ::DynHandleMut)
+                    }
+                },
+            ));
+        }
+
+        let mut concrete_bounds: Vec<TokenStream> = Vec::new();
+        if has_optional {
+            concrete_bounds.push(quote!(#tinydyn ::Overrides<#trait_object>));
+        }
+        let self_ref_ptr = quote!(#private ::SelfPtr<*const #trait_object>);
+        let metadata = quote!(<Self as #tinydyn ::PlainDyn>::Metadata);
+        // Equality uses the `TypeId` to check that two trait objects have the same concrete type.
+        if args.downcast || args.partial_eq || args.eq {
+            concrete_bounds.push(quote!('static));
+            vtable_entries.push(VtableEntry {
+                // A wrapped std trait object can't be downcast.
+                std_builder: Some(quote!(core::any::TypeId::of::<#private ::StdObject>)),
+                ..VtableEntry::new(
+                    format_ident!("__tinydyn_type_id"),
+                    quote!(fn() -> core::any::TypeId),
+                    quote!(core::any::TypeId::of::<#concrete>),
+                )
+            });
+            extra_impls.push(quote!(
+                unsafe impl #tinydyn ::DynAny for #trait_object {
+                    #[inline(always)]
+                    fn type_id(#meta_local: #metadata) -> core::any::TypeId {
+                        (#meta_local.__tinydyn_type_id)()
+                    }
+                }
+            ));
+        }
+        if args.partial_eq || args.eq {
+            let eq_trait = if args.eq {
+                quote!(core::cmp::Eq)
+            } else {
+                quote!(core::cmp::PartialEq)
+            };
+            concrete_bounds.push(quote!(#eq_trait));
+            vtable_entries.push(VtableEntry::new(
+                format_ident!("__tinydyn_eq"),
+                quote!(fn(#self_ref_ptr, #self_ref_ptr) -> bool),
+                quote!(core::mem::transmute(
+                    <#concrete as core::cmp::PartialEq>::eq as *const ())),
+            ));
+            extra_impls.push(quote!(
+                unsafe impl #tinydyn ::DynPartialEq for #trait_object {
+                    #[inline(always)]
+                    unsafe fn eq(#meta_local: #metadata, lhs: #self_ref_ptr, rhs: #self_ref_ptr) -> bool {
+                        (#meta_local.__tinydyn_eq)(lhs, rhs)
+                    }
+                }
+            ));
+            if args.eq {
+                extra_impls.push(quote!(unsafe impl #tinydyn ::DynEq for #trait_object {}));
+            }
+        }
+        if args.query {
+            concrete_bounds.push(quote!(#tinydyn ::Provides<#trait_object>));
+            vtable_entries.push(VtableEntry {
+                eq: Some(quote!(core::ptr::eq(
+                    self.__tinydyn_provided,
+                    other.__tinydyn_provided
+                ))),
+                std_builder: Some(quote!(&[])),
+                ..VtableEntry::new(
+                    format_ident!("__tinydyn_provided"),
+                    quote!(&'static [#tinydyn ::Provided]),
+                    quote!(<#concrete as #tinydyn ::Provides<#trait_object>>::PROVIDED),
+                )
+            });
+            extra_impls.push(quote!(
+                unsafe impl #tinydyn ::DynQuery for #trait_object {
+                    #[inline(always)]
+                    fn provided(#meta_local: #metadata) -> &'static [#tinydyn ::Provided] {
+                        #meta_local.__tinydyn_provided
                     }
                 }
             ));
         }
+        if args.hash {
+            let state = quote!(#tinydyn ::RefMut<'_, dyn #tinydyn ::DynHasher>);
+            concrete_bounds.push(quote!(core::hash::Hash));
+            vtable_entries.push(VtableEntry::new(
+                format_ident!("__tinydyn_hash"),
+                quote!(fn(#self_ref_ptr, #state)),
+                quote!(core::mem::transmute(
+                    #private ::hash_thunk::<#concrete> as *const ())),
+            ));
+            extra_impls.push(quote!(
+                unsafe impl #tinydyn ::DynHash for #trait_object {
+                    #[inline(always)]
+                    unsafe fn hash(#meta_local: #metadata, #self_local: #self_ref_ptr, state: #state) {
+                        (#meta_local.__tinydyn_hash)(#self_local, state)
+                    }
+                }
+            ));
+        }
+
+        // A `Debug` or `Display` supertrait formats the concrete value through the vtable.
+        let no_fmt = quote!({
+            let _ = (#meta_local, #self_local, f);
+            None
+        });
+        let mut fmt_getters = [no_fmt.clone(), no_fmt];
+        for fmt_trait in &fmt_traits {
+            if repr_c {
+                return Err(Error::new(
+                    fmt_trait.span(),
+                    "`Debug` and `Display` supertraits can't be used with `repr_c` or `stable_abi`",
+                ));
+            }
+            let name = fmt_trait.to_string().to_lowercase();
+            let entry_ident = format_ident!("__tinydyn_{name}");
+            let thunk = format_ident!("{name}_thunk");
+            let dyn_trait = format_ident!("Dyn{fmt_trait}");
+            concrete_bounds.push(quote!(core::fmt::#fmt_trait));
+            let builder = quote!(core::mem::transmute(
+                #private ::#thunk::<#concrete> as *const ()));
+            vtable_entries.push(VtableEntry {
+                std_builder: Some(builder.clone()),
+                ..VtableEntry::new(
+                    entry_ident.clone(),
+                    quote!(fn(#self_ref_ptr, &mut core::fmt::Formatter<'_>) -> core::fmt::Result),
+                    builder,
+                )
+            });
+            extra_impls.push(quote!(unsafe impl #tinydyn ::#dyn_trait for #trait_object {}));
+            fmt_getters[usize::from(fmt_trait == "Display")] =
+                quote!(Some((#meta_local.#entry_ident)(#self_local, f)));
+        }
+        let [fmt_debug, fmt_display] = fmt_getters;
 
+        let entry_idents = vtable_entries.iter().map(|entry| &entry.ident);
+        let entry_builders = vtable_entries.iter().map(|entry| &entry.builder);
+        let inline = args.stable_abi.is_none()
+            && vtable_entries.len() <= 1
+            && !has_optional
+            && marked_entries.is_empty();
+        let type_name = (!repr_c && !inline).then(|| format_ident!("__tinydyn_type_name"));
+        let type_name_iter = type_name.iter();
+        let abi_header = args
+            .stable_abi
+            .as_ref()
+            .map(|StableAbiArgs { version, .. }| {
+                quote!(#tinydyn ::AbiHeader {
+                    size: core::mem::size_of::<#vtable_ident>() as u32,
+                    version: #version,
+                })
+            });
+        let abi_header_iter = abi_header.iter();
         let vtable_build_expr = quote!(
             unsafe {
                 #vtable_ident {
-                    #(#vtable_builders,)*
+                    #(__tinydyn_header: #abi_header_iter,)*
+                    #(#entry_idents: #entry_builders,)*
+                    #(#type_name_iter: #private ::TypeName::of::<#concrete>(),)*
                 }
             }
         );
         let static_vtable_type; // This is statically alloc'd for every (trait, concrete).
         let static_vtable_expr; // This builds the above.
         let metadata_type; // This extra data is carried along in DynPtr.
+        let metadata_from_vtable; // Converts a `&'static` vtable into the above.
         let metadata_getter; // When building a wide pointer, this gets the metadata.
+        let vtable_addr; // Identifies the vtable for a given `meta`.
 
-        if fn_items.len() <= 1 {
+        if args.stable_abi.is_some() {
+            static_vtable_type = vtable_ident.to_token_stream();
+            static_vtable_expr = quote!(Self::VTABLE);
+            metadata_type = quote!(#private ::VtablePtr<#vtable_ident>);
+            metadata_from_vtable = quote!(#private ::VtablePtr::new(vtable));
+            metadata_getter = quote!(#private ::VtablePtr::new(&Self::STATIC_VTABLE));
+            vtable_addr = quote!(meta.as_ptr() as *const ());
+        } else if inline {
             static_vtable_type = quote!(#private ::InlineVTable);
             static_vtable_expr = static_vtable_type.clone();
             metadata_type = vtable_ident.to_token_stream();
-            metadata_getter = vtable_build_expr;
+            metadata_from_vtable = quote!(*vtable);
+            metadata_getter = quote!(Self::VTABLE);
+            // The only function pointer identifies the vtable.
+            vtable_addr = match vtable_entries.first() {
+                Some(VtableEntry { ident, .. }) => quote!(meta.#ident as *const ()),
+                None => quote!(core::ptr::null()),
+            };
         } else {
             static_vtable_type = vtable_ident.to_token_stream();
-            static_vtable_expr = vtable_build_expr;
+            static_vtable_expr = quote!(Self::VTABLE);
             metadata_type = quote!(&'static #vtable_ident);
+            metadata_from_vtable = quote!(vtable);
             metadata_getter = quote!(&Self::STATIC_VTABLE);
+            vtable_addr = quote!(meta as *const #vtable_ident as *const ());
         }
 
+        let mut helper_traits = Vec::new();
+        if args.repr_c {
+            let c_vtable_ident = format_ident!("{trait_ident}CVtable");
+            let doc = format!(
+                " `{vtable_ident}` as C provides it, for `Ref::from_c`. \
+                It has the same layout, but each function may be null and takes raw pointers \
+                instead of references.\n\n \
+                A `{vtable_ident}` declared by `{vtable_ident}::C_HEADER` can be cast to it."
+            );
+            helper_traits.push((
+                c_vtable_ident.clone(),
+                quote!(
+                    #[doc = #doc]
+                    #[derive(Copy, Clone, Debug)]
+                    #[repr(C)]
+                    pub struct #c_vtable_ident {
+                        #(#c_vtable_fields)*
+                    }
+
+                    unsafe impl #tinydyn ::ReprC for #trait_object {
+                        type CVtable = #c_vtable_ident;
+                    }
+                ),
+            ));
+        }
+        if let Some(StableAbiArgs {
+            version,
+            min_version,
+        }) = &args.stable_abi
+        {
+            let min_size = match &last_required {
+                Some(entry_ident) => quote!(
+                    core::mem::offset_of!(#vtable_ident, #entry_ident)
+                        + core::mem::size_of::<*const ()>()
+                ),
+                None => quote!(core::mem::size_of::<#tinydyn ::AbiHeader>()),
+            };
+            let signatures = c_methods
+                .iter()
+                .map(|method| proc_macro2::Literal::u64_suffixed(method.fingerprint()));
+            extra_impls.push(quote!(
+                unsafe impl #tinydyn ::StableAbi for #trait_object {
+                    const VERSION: u32 = #version;
+                    const MIN_VERSION: u32 = #min_version;
+                    const MIN_SIZE: usize = #min_size;
+                    const SIGNATURES: &'static [u64] = &[#(#signatures),*];
+
+                    #[inline(always)]
+                    fn vtable_ptr(
+                        #meta_local: #private ::VtablePtr<#vtable_ident>,
+                    ) -> core::ptr::NonNull<#vtable_ident> {
+                        #meta_local.as_non_null()
+                    }
+
+                    #[inline(always)]
+                    unsafe fn metadata_from_ptr(
+                        vtable: core::ptr::NonNull<#vtable_ident>,
+                    ) -> #private ::VtablePtr<#vtable_ident> {
+                        unsafe { #private ::VtablePtr::from_ptr(vtable) }
+                    }
+                }
+            ));
+            if !versioned_methods.is_empty() {
+                let doc = format!(
+                    " The methods of `{trait_ident}` added after version {min_version}, \
+                    which an imported vtable may be missing."
+                );
+                helper_traits.push(names.helper_trait(
+                    format_ident!("{trait_ident}Versioned"),
+                    &doc,
+                    &versioned_decls,
+                    &versioned_methods,
+                ));
+            }
+        }
+        if !optional_methods.is_empty() {
+            let doc = format!(
+                " Queries which `optional` methods of `{trait_ident}` the concrete type overrides."
+            );
+            helper_traits.push(names.helper_trait(
+                format_ident!("{trait_ident}Optional"),
+                &doc,
+                &optional_decls,
+                &optional_methods,
+            ));
+        }
+        // A single `&self` method can be called from C as a callback.
+        // A stable vtable is never inline, so it can't be.
+        if let ([(entry_ident, callback_ty)], 1, true) =
+            (&c_callbacks[..], vtable_entries.len(), args.repr_c)
+        {
+            extra_impls.push(quote!(
+                unsafe impl #tinydyn ::CCallback for #trait_object {
+                    type Callback = #callback_ty;
+
+                    #[inline(always)]
+                    fn callback(#meta_local: #vtable_ident) -> #callback_ty {
+                        // SAFETY: `SelfPtr` is a transparent non-null pointer.
+                        unsafe { core::mem::transmute(#meta_local.#entry_ident) }
+                    }
+                }
+            ));
+        }
+        let static_supertrait = supertraits
+            .iter()
+            .find(|bound| matches!(bound, TypeParamBound::Lifetime(_)));
+        if let (true, Some(bound)) = (args.ref_impls, static_supertrait) {
+            // Method calls on a shorter-lived `Ref` would pick an impl requiring `Ref: 'static`,
+            // rather than deref to the trait object.
+            return Err(Error::new(
+                bound.span(),
+                "`ref_impls` can't be used with a `'static` supertrait",
+            ));
+        }
+        // Converting to and from std trait objects, if every entry can call through a std vtable.
+        let std_builders: Option<Vec<_>> = vtable_entries
+            .iter()
+            .map(|entry| entry.std_builder.as_ref())
+            .collect();
+        let std_dyn = std_builders
+            .filter(|_| !repr_c && marked_entries.is_empty())
+            .map(|std_builders| {
+                let std_ident = format_ident!("{trait_ident}Std");
+                let entry_idents = vtable_entries.iter().map(|entry| &entry.ident);
+                let type_name_iter = type_name.iter();
+                let std_vtable = quote!(
+                    unsafe {
+                        #vtable_ident {
+                            #(#entry_idents: #std_builders,)*
+                            #(#type_name_iter: #private ::TypeName::of::<#concrete>(),)*
+                        }
+                    }
+                );
+                let [std_ref_metadata, std_mut_metadata] = [quote!(&'static), quote!(&'static mut)]
+                    .map(|std_ptr| {
+                        quote!({
+                            type #concrete = #std_ptr #trait_object;
+                            const VTABLE: #vtable_ident = #std_vtable;
+                            let vtable: &'static #vtable_ident = &VTABLE;
+                            #metadata_from_vtable
+                        })
+                    });
+                // `&mut self` methods can only be called through a mutable pointer.
+                let adapter_ptr = if has_mut_method {
+                    quote!(core::ops::DerefMut)
+                } else {
+                    quote!(core::ops::Deref)
+                };
+                // A `'static` supertrait fixes the lifetime of the std trait object.
+                let std_lifetime = static_supertrait.is_none().then(|| quote!(+ 'b));
+                let doc = format!(
+                    " Implements `{trait_ident}` by calling through the pointer `P`, like a \
+                    tinydyn `Ref<dyn {trait_ident}>`, so it can be coerced to a \
+                    `&dyn {trait_ident}`."
+                );
+                let tokens = quote!(
+                    #[doc = #doc]
+                    #[repr(transparent)]
+                    pub struct #std_ident<P>(pub P);
+
+                    impl<P> #trait_ident for #std_ident<P>
+                    where
+                        P: #adapter_ptr,
+                        P::Target: #trait_ident,
+                        Self: #supertraits,
+                    {
+                        #(#adapter_methods)*
+                    }
+
+                    #(
+                        impl<P> core::fmt::#fmt_traits for #std_ident<P>
+                        where
+                            P: core::ops::Deref,
+                            P::Target: core::fmt::#fmt_traits,
+                        {
+                            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                                core::fmt::#fmt_traits::fmt(&*self.0, f)
+                            }
+                        }
+                    )*
+
+                    #(#std_thunks)*
+
+                    unsafe impl #tinydyn ::StdDyn for #trait_object {
+                        type Std<'b> = dyn #trait_ident #std_lifetime;
+                        type Adapter<P> = #std_ident<P>;
+
+                        const STD_REF_METADATA: #metadata_type = #std_ref_metadata;
+                        const STD_MUT_METADATA: #metadata_type = #std_mut_metadata;
+
+                        #[inline(always)]
+                        fn adapter<P>(ptr: P) -> #std_ident<P> {
+                            #std_ident(ptr)
+                        }
+                    }
+                );
+                (std_ident, tokens)
+            });
+
+        let c_header = repr_c.then(|| {
+            c_header::c_header(
+                trait_ident,
+                &c_methods,
+                args.stable_abi.is_some(),
+                vtable_entries.len() <= 1 && args.stable_abi.is_none(),
+            )
+        });
+
         Ok(Self {
+            vis,
+            repr_c,
+            type_name,
+            c_header,
+            markers: args.markers,
+            supertraits,
+            marked_entries,
+            fmt_debug,
+            fmt_display,
+            abi_header,
+            helper_traits,
+            std_dyn,
             vtable_entries,
             vtable_callers,
+            handle_methods,
+            has_mut_method,
+            ref_impls: args.ref_impls,
+            concrete_bounds,
+            extra_impls,
+            vtable_build_expr,
             static_vtable_type,
             static_vtable_expr,
             metadata_type,
+            metadata_from_vtable,
             metadata_getter,
+            vtable_addr,
             names,
         })
 
@@ -576,6 +1881,136 @@ impl TinydynImplModule {
     }
 }
 
+/// Checks the `since` version of each method, returning them in order.
+///
+/// A `stable_abi` trait must give every method a `since`, which can't decrease, so methods are
+/// only ever appended to its vtable. Methods added after `min_version` need a default body to
+/// fall back to when an older vtable is missing them.
+fn method_versions(fn_items: &[TraitItemFn], args: &TraitArgs) -> Result<Vec<Option<u32>>> {
+    let Some(stable_abi) = &args.stable_abi else {
+        if let Some(since) = args.methods.iter().flat_map(|method| &method.since).next() {
+            return Err(Error::new(
+                since.span(),
+                "`since` requires `#[tinydyn(stable_abi(..))]` on the trait",
+            ));
+        }
+        return Ok(vec![None; fn_items.len()]);
+    };
+    let mut last = 1;
+    fn_items
+        .iter()
+        .zip(&args.methods)
+        .map(|(fn_item, MethodArgs { since, .. })| {
+            let Some(since) = since else {
+                return Err(Error::new(
+                    fn_item.sig.ident.span(),
+                    "methods of a `stable_abi` trait must be marked `#[tinydyn(since = N)]`",
+                ));
+            };
+            if fn_item.sig.ident == "header" {
+                return Err(Error::new(
+                    fn_item.sig.ident.span(),
+                    "`header` is the `TinydynAbiHeader` field of a `stable_abi` vtable in C",
+                ));
+            }
+            let version = since.base10_parse::<u32>()?;
+            if version < last || version > stable_abi.version {
+                return Err(Error::new(
+                    since.span(),
+                    format!(
+                        "`since` must be from {last} to {}; new methods must go at the end",
+                        stable_abi.version
+                    ),
+                ));
+            }
+            if version > stable_abi.min_version && fn_item.default.is_none() {
+                return Err(Error::new(
+                    since.span(),
+                    "methods added after `min_version` must have a default body to fall back to",
+                ));
+            }
+            last = version;
+            Ok(Some(version))
+        })
+        .collect()
+}
+
+/// A method signature with `self` replaced by a concrete type and its elided lifetimes named.
+struct ConcreteSig {
+    lifetimes: Vec<syn::Lifetime>,
+    inputs: Vec<TokenStream>,
+    output: syn::ReturnType,
+}
+
+impl ConcreteSig {
+    /// Converts `sig` to take `concrete`, like
+    /// `fn<'__tinydyn_self>(&'__tinydyn_self Concrete, &str) -> &'__tinydyn_self str`.
+    fn new(sig: &syn::Signature, concrete: &TokenStream) -> Self {
+        use syn::fold::Fold;
+        /// Names the lifetimes elided in a method's output, which are those of `self`.
+        struct ElideTo(syn::Lifetime);
+        impl Fold for ElideTo {
+            fn fold_lifetime(&mut self, lt: syn::Lifetime) -> syn::Lifetime {
+                if lt.ident == "_" {
+                    self.0.clone()
+                } else {
+                    lt
+                }
+            }
+            fn fold_type_reference(&mut self, i: syn::TypeReference) -> syn::TypeReference {
+                let mut i = syn::fold::fold_type_reference(self, i);
+                i.lifetime.get_or_insert_with(|| self.0.clone());
+                i
+            }
+        }
+
+        let mut lifetimes: Vec<syn::Lifetime> = sig
+            .generics
+            .lifetimes()
+            .map(|param| param.lifetime.clone())
+            .collect();
+        let mut self_lifetime = None;
+        let inputs = sig
+            .inputs
+            .iter()
+            .map(|arg| match arg {
+                syn::FnArg::Receiver(receiver) => {
+                    let syn::Type::Reference(elem) = &*receiver.ty else {
+                        unreachable!("receivers are checked to be references")
+                    };
+                    let lifetime = elem.lifetime.clone().unwrap_or_else(|| {
+                        let lt = syn::Lifetime::new("'__tinydyn_self", Span::mixed_site());
+                        lifetimes.push(lt.clone());
+                        lt
+                    });
+                    let mutability = elem.mutability;
+                    self_lifetime = Some(lifetime.clone());
+                    quote!(&#lifetime #mutability #concrete)
+                }
+                syn::FnArg::Typed(pat_type) => pat_type.ty.to_token_stream(),
+            })
+            .collect();
+        let self_lifetime = self_lifetime.expect("methods are checked to have a receiver");
+        let output = ElideTo(self_lifetime).fold_return_type(sig.output.clone());
+        Self {
+            lifetimes,
+            inputs,
+            output,
+        }
+    }
+
+    /// The type of a function that can fill the vtable entry for `sig` in a vtable builder.
+    fn fn_pointer(&self, sig: &syn::Signature, abi: &Option<syn::Abi>) -> TokenStream {
+        let Self {
+            lifetimes,
+            inputs,
+            output,
+        } = self;
+        let unsafety = &sig.unsafety;
+        quote!(for<#(#lifetimes),*> #unsafety #abi fn(#(#inputs),*) #output)
+    }
+}
+
 /// Returns (bare fn type, whether it needed the conversion)
 fn to_bare_arg_type(arg_type: &syn::Type) -> Result<(Box<syn::Type>, BareConversionNeeded)> {
     use syn::fold::Fold;
@@ -611,8 +2046,8 @@ fn to_bare_arg_type(arg_type: &syn::Type) -> Result<(Box<syn::Type>, BareConvers
     Ok((bare_type, BareConversionNeeded(needed_replace)))
 }
 
-fn tinydyn_mod_impl(trait_item: ItemTrait) -> Result<TokenStream> {
-    TinydynImplModule::new(trait_item).map(ToTokens::into_token_stream)
+fn tinydyn_mod_impl(trait_item: ItemTrait, args: TraitArgs) -> Result<TokenStream> {
+    TinydynImplModule::new(trait_item, args).map(ToTokens::into_token_stream)
 }
 
 /// This trait is `tinydyn`-compatible.
@@ -621,26 +2056,132 @@ pub fn tinydyn(
     params: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    if let Some(first_tt) = params.into_iter().next() {
-        return quote_spanned!(
-            first_tt.span().into()=>
-            compile_error!("params must be empty");
-        )
-        .into();
-    }
-    let original_tokens = item.clone();
-    let input = parse_macro_input!(item as ItemTrait);
-    tinydyn_mod_impl(input)
+    let mut args = match TraitArgs::parse(params.into()) {
+        Ok(args) => args,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let mut input = parse_macro_input!(item as ItemTrait);
+    args.methods = match take_method_attrs(&mut input) {
+        Ok(methods) => methods,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let trait_tokens = proc_macro::TokenStream::from(input.to_token_stream());
+    tinydyn_mod_impl(input, args)
         .map(move |mod_impl| {
             let mut mod_impl = proc_macro::TokenStream::from(mod_impl);
             mod_impl.extend([
                 "#[deny(elided_lifetimes_in_paths)]"
                     .parse::<proc_macro::TokenStream>()
-                    .unwrap()
-                    .into(),
-                original_tokens,
+                    .unwrap(),
+                trait_tokens,
             ]);
             mod_impl
         })
         .unwrap_or_else(|e| e.into_compile_error().into())
 }
+
+/// The arguments to `vtable!`, `dyn Trait for Type`.
+struct VtableArgs {
+    trait_object: syn::Type,
+    concrete: syn::Type,
+}
+
+impl syn::parse::Parse for VtableArgs {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self> {
+        let trait_object = input.parse()?;
+        input.parse::<Token![for]>()?;
+        let concrete = input.parse()?;
+        Ok(Self {
+            trait_object,
+            concrete,
+        })
+    }
+}
+
+/// Gets the `&'static` vtable of a tinydyn trait for a concrete type.
+#[proc_macro]
+pub fn vtable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let VtableArgs {
+        trait_object,
+        concrete,
+    } = parse_macro_input!(input as VtableArgs);
+    quote!(tinydyn::vtable_for::<#trait_object, #concrete>()).into()
+}
+
+/// Records which methods an impl of a tinydyn trait overrides, implementing `Overrides`.
+#[proc_macro_attribute]
+pub fn overrides(
+    params: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let params = TokenStream::from(params);
+    if !params.is_empty() {
+        return Error::new_spanned(params, "`overrides` takes no options")
+            .into_compile_error()
+            .into();
+    }
+    let item_impl = parse_macro_input!(item as syn::ItemImpl);
+    let Some((None, trait_path, _)) = &item_impl.trait_ else {
+        return Error::new_spanned(
+            &item_impl.self_ty,
+            "`overrides` must be on an impl of a tinydyn trait",
+        )
+        .into_compile_error()
+        .into();
+    };
+    let overridden = item_impl.items.iter().filter_map(|item| match item {
+        syn::ImplItem::Fn(fn_item) => Some(fn_item.sig.ident.to_string()),
+        _ => None,
+    });
+    let (impl_generics, _, where_clause) = item_impl.generics.split_for_impl();
+    let self_ty = &item_impl.self_ty;
+    quote!(
+        #item_impl
+
+        impl #impl_generics tinydyn::Overrides<dyn #trait_path> for #self_ty #where_clause {
+            const OVERRIDDEN: &'static [&'static str] = &[#(#overridden),*];
+        }
+    )
+    .into()
+}
+
+/// Lists the other tinydyn traits a type provides to a `#[tinydyn(query)]` trait, implementing
+/// `Provides`.
+#[proc_macro_attribute]
+pub fn provides(
+    params: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let provided =
+        parse_macro_input!(params with Punctuated::<syn::Type, Token![,]>::parse_terminated);
+    let item_impl = parse_macro_input!(item as syn::ItemImpl);
+    let Some((None, trait_path, _)) = &item_impl.trait_ else {
+        return Error::new_spanned(
+            &item_impl.self_ty,
+            "`provides` must be on an impl of a tinydyn trait",
+        )
+        .into_compile_error()
+        .into();
+    };
+    let provided = provided.iter();
+    let (impl_generics, _, where_clause) = item_impl.generics.split_for_impl();
+    let self_ty = &item_impl.self_ty;
+    quote!(
+        #item_impl
+
+        // SAFETY: Each vtable is built for `Self`.
+        unsafe impl #impl_generics tinydyn::Provides<dyn #trait_path> for #self_ty #where_clause {
+            const PROVIDED: &'static [tinydyn::Provided] =
+                &[#(tinydyn::Provided::new::<#provided, Self>()),*];
+        }
+    )
+    .into()
+}
+
+/// Declares a trait combining tinydyn traits, whose trait object can call all of their methods.
+#[proc_macro]
+pub fn combine(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    parse_macro_input!(input as combine::Combine)
+        .expand()
+        .into()
+}
@@ -0,0 +1,153 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generates the impls for a trait object with marker traits, like `dyn Trait + Send`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Error, Result};
+
+/// The auto traits that can be added to a trait object with `+`.
+const AUTO_TRAITS: &[&str] = &["Send", "Sync", "Unpin", "UnwindSafe", "RefUnwindSafe"];
+
+/// Whether `path` names an auto trait, like `Send` or `core::marker::Send`.
+pub(crate) fn is_auto_trait(path: &syn::Path) -> bool {
+    let last = path.segments.last().unwrap();
+    last.arguments.is_none() && AUTO_TRAITS.iter().any(|auto| last.ident == auto)
+}
+
+/// Whether `a` and `b` name the same marker trait, like `Send` and `core::marker::Send`.
+pub(crate) fn same_trait(a: &syn::Path, b: &syn::Path) -> bool {
+    a.segments.last().unwrap().ident == b.segments.last().unwrap().ident
+}
+
+/// The marker traits a tinydyn trait object can carry.
+pub(crate) struct Markers {
+    /// Auto traits, added like `dyn Trait + Send`. This always has `Send` and `Sync`.
+    auto: Vec<syn::Path>,
+    /// Other marker traits, added like `Marked<dyn Trait, dyn IsrSafe>`.
+    user: Vec<syn::Path>,
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Self {
+            auto: vec![syn::parse_quote!(Send), syn::parse_quote!(Sync)],
+            user: Vec::new(),
+        }
+    }
+}
+
+impl Markers {
+    /// Whether `marker` is one of the auto traits the trait object can carry.
+    pub(crate) fn has_auto(&self, marker: &syn::Path) -> bool {
+        self.auto.iter().any(|auto| same_trait(auto, marker))
+    }
+
+    /// Adds a marker listed in `markers(..)`.
+    pub(crate) fn add(&mut self, marker: syn::Path) -> Result<()> {
+        if self
+            .auto
+            .iter()
+            .chain(&self.user)
+            .any(|existing| same_trait(existing, &marker))
+        {
+            return Err(Error::new_spanned(marker, "duplicate marker"));
+        }
+        if is_auto_trait(&marker) {
+            self.auto.push(marker);
+        } else {
+            self.user.push(marker);
+        }
+        Ok(())
+    }
+
+    /// Implements `DynTrait`, `Implements` and `RemoveMarker` for `trait_object` with every
+    /// combination of markers.
+    ///
+    /// `concrete_bounds` gives the where clauses a concrete type `Concrete` must meet to be cast
+    /// to `trait_object` with the given auto traits, besides those traits themselves.
+    /// `marked_vtable` gives the items of the `Implements` impl for that marked trait object,
+    /// which may override its marked vtable.
+    pub(crate) fn impls(
+        &self,
+        trait_object: &TokenStream,
+        newtype: &syn::Ident,
+        concrete_bounds: impl Fn(&[&syn::Path]) -> TokenStream,
+        marked_vtable: impl Fn(&TokenStream, &[&syn::Path]) -> TokenStream,
+    ) -> TokenStream {
+        let tinydyn = quote!(tinydyn);
+        let mut impls = TokenStream::new();
+        let with_markers = |mask: usize| {
+            let markers = self
+                .auto
+                .iter()
+                .enumerate()
+                .filter_map(move |(i, marker)| (mask & (1 << i) != 0).then_some(marker));
+            quote!(#trait_object #(+ #markers)*)
+        };
+        for mask in 0..(1usize << self.auto.len()) {
+            let object = with_markers(mask);
+            let markers: Vec<_> = self
+                .auto
+                .iter()
+                .enumerate()
+                .filter_map(|(i, marker)| (mask & (1 << i) != 0).then_some(marker))
+                .collect();
+            let concrete_bounds = concrete_bounds(&markers);
+            let marked_vtable = marked_vtable(&object, &markers);
+            impls.extend(quote!(
+                unsafe impl #tinydyn ::DynTrait for #object {
+                    type Plain = #trait_object;
+                }
+
+                unsafe impl<Concrete> #tinydyn ::Implements<#object> for #newtype <Concrete>
+                where
+                    #concrete_bounds
+                    Concrete: #(#markers +)*,
+                {
+                    #marked_vtable
+                }
+            ));
+            for (i, marker) in self.auto.iter().enumerate() {
+                if mask & (1 << i) == 0 {
+                    continue;
+                }
+                let output = with_markers(mask & !(1 << i));
+                impls.extend(quote!(
+                    unsafe impl #tinydyn ::RemoveMarker<dyn #marker> for #object {
+                        type Output = #output;
+                    }
+                ));
+            }
+        }
+        for marker in &self.user {
+            impls.extend(quote!(
+                unsafe impl<Trait, Concrete> #tinydyn ::Implements<#tinydyn ::Marked<Trait, dyn #marker>>
+                    for #newtype <Concrete>
+                where
+                    Trait: ?Sized + #tinydyn ::DynTrait<Plain = #trait_object>,
+                    #newtype <Concrete>: #tinydyn ::Implements<Trait>,
+                    Concrete: #marker,
+                {
+                    const MARKED_VTABLE_REF: &'static #tinydyn ::VTable<Trait> =
+                        <#newtype <Concrete> as #tinydyn ::Implements<Trait>>::MARKED_VTABLE_REF;
+                    const MARKED_METADATA: <#trait_object as #tinydyn ::PlainDyn>::Metadata =
+                        <#newtype <Concrete> as #tinydyn ::Implements<Trait>>::MARKED_METADATA;
+                }
+            ));
+        }
+        impls
+    }
+}
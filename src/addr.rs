@@ -0,0 +1,159 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Comparing and formatting tinydyn trait objects by their address.
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+use crate::{DynPtr, DynTrait, PlainDyn, Ref, RefMut};
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> DynPtr<'a, Trait> {
+    /// The address of the data and vtable, which identify a trait object.
+    fn identity(&self) -> (*const (), *const ()) {
+        let vtable = <Trait::Plain as PlainDyn>::vtable_addr(self.meta);
+        (self.data.as_ptr() as *const (), vtable)
+    }
+
+    /// Prints the concrete value with its `Debug` impl, if the trait has one, or the addresses.
+    fn fmt_debug(&self, name: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // SAFETY: `self.data` points to the concrete type the metadata was built for.
+        match unsafe { <Trait::Plain as PlainDyn>::fmt_debug(self.meta, self.self_ref(), f) } {
+            Some(result) => result,
+            None => self.fmt_as(name, f),
+        }
+    }
+
+    /// Prints the data and vtable addresses, and the type name if it's known.
+    fn fmt_as(&self, name: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (data, vtable) = self.identity();
+        let mut out = f.debug_struct(name);
+        out.field("data", &data).field("vtable", &vtable);
+        if let Some(type_name) = <Trait::Plain as PlainDyn>::type_name(self.meta) {
+            out.field("type_name", &type_name);
+        }
+        out.finish()
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> Ref<'a, Trait> {
+    /// Returns `true` if the two `Ref`s point to the same data with the same vtable.
+    ///
+    /// Like comparing `&dyn Trait` pointers, this can give false negatives: the same vtable
+    /// may be duplicated across codegen units.
+    ///
+    /// This is an associated function to not shadow methods of `Trait`.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.inner.identity() == other.inner.identity()
+    }
+
+    /// Gets the address of the data this `Ref` points to.
+    ///
+    /// This is an associated function to not shadow methods of `Trait`.
+    pub fn addr(this: &Self) -> usize {
+        this.inner.data.as_ptr().addr()
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> RefMut<'a, Trait> {
+    /// Gets the address of the data this `RefMut` points to.
+    ///
+    /// This is an associated function to not shadow methods of `Trait`.
+    pub fn addr(this: &Self) -> usize {
+        this.inner.data.as_ptr().addr()
+    }
+}
+
+/// Prints the concrete value if `Trait` has a [`Debug`](fmt::Debug) supertrait. Otherwise,
+/// prints the data and vtable addresses, as well as the concrete type name with the
+/// `type_name` feature. Inline vtables of one method leave out the name, so they stay the size
+/// of a function pointer.
+impl<'a, Trait: ?Sized + DynTrait + 'a> fmt::Debug for Ref<'a, Trait> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt_debug("Ref", f)
+    }
+}
+
+/// Prints the concrete value if `Trait` has a [`Debug`](fmt::Debug) supertrait. Otherwise,
+/// prints the data and vtable addresses, as well as the concrete type name with the
+/// `type_name` feature. Inline vtables of one method leave out the name, so they stay the size
+/// of a function pointer.
+impl<'a, Trait: ?Sized + DynTrait + 'a> fmt::Debug for RefMut<'a, Trait> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt_debug("RefMut", f)
+    }
+}
+
+/// Wraps a [`Ref`] to compare, hash and order it by its identity instead of its value.
+///
+/// The identity is the data address and the vtable address, as in [`Ref::ptr_eq`].
+/// This lets handles be kept in sets and removed by identity.
+#[repr(transparent)]
+pub struct ByAddress<'a, Trait: ?Sized + DynTrait>(pub Ref<'a, Trait>);
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> Copy for ByAddress<'a, Trait> {}
+impl<'a, Trait: ?Sized + DynTrait + 'a> Clone for ByAddress<'a, Trait> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> From<Ref<'a, Trait>> for ByAddress<'a, Trait> {
+    fn from(value: Ref<'a, Trait>) -> Self {
+        Self(value)
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> Deref for ByAddress<'a, Trait> {
+    type Target = Ref<'a, Trait>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> PartialEq for ByAddress<'a, Trait> {
+    fn eq(&self, other: &Self) -> bool {
+        Ref::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> Eq for ByAddress<'a, Trait> {}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> Hash for ByAddress<'a, Trait> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.inner.identity().hash(state)
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> PartialOrd for ByAddress<'a, Trait> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait + 'a> Ord for ByAddress<'a, Trait> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.inner.identity().cmp(&other.0.inner.identity())
+    }
+}
+
+/// Prints the data and vtable addresses, even if `Trait` has a [`Debug`](fmt::Debug) supertrait.
+impl<'a, Trait: ?Sized + DynTrait + 'a> fmt::Debug for ByAddress<'a, Trait> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.inner.fmt_as("ByAddress", f)
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checked downcasting of tinydyn trait objects to their concrete type.
+
+use core::any::TypeId;
+
+use crate::{DynTrait, PlainDyn, Ref, RefMut};
+
+/// A tinydyn trait object that knows the [`TypeId`] of its concrete type.
+///
+/// Implemented by `#[tinydyn(downcast)]`, as well as `#[tinydyn(partial_eq)]` and
+/// `#[tinydyn(eq)]`, which all require every implementer to be `'static`.
+/// This enables [`Ref::downcast_ref`] and [`RefMut::downcast_mut`].
+///
+/// Only `'static` implementers can be downcast. A per-type tag for non-`'static` types, like the
+/// address of a static per concrete type, isn't possible:
+///
+/// - Rust has no generic statics, and the addresses of functions or promoted constants aren't
+///   guaranteed to be unique per type.
+/// - Even a unique tag would be shared by `T<'a>` and `T<'b>`, which differ only in lifetimes.
+///   So a check against it would let a `Ref` to a `T<'short>` downcast to a `T<'static>`, which
+///   is unsound.
+///
+/// # Safety
+/// `type_id` must return the [`TypeId`] of the concrete type `meta` was built for.
+pub unsafe trait DynAny: PlainDyn {
+    /// Gets the [`TypeId`] of the concrete type this metadata was built for.
+    #[doc(hidden)]
+    fn type_id(meta: Self::Metadata) -> TypeId;
+}
+
+impl<'a, Trait> Ref<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynAny,
+{
+    /// Returns `true` if the concrete type of this trait object is `T`.
+    pub fn is<T: 'static>(&self) -> bool {
+        <Trait::Plain as DynAny>::type_id(self.inner.meta) == TypeId::of::<T>()
+    }
+
+    /// Downcasts to the concrete type `T`, or `None` if it's not a `T`.
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&'a T> {
+        if !self.is::<T>() {
+            return None;
+        }
+        // SAFETY: This `Ref` was built from a `&'a T`, as checked by the `TypeId` above.
+        Some(unsafe { self.inner.data.cast().as_ref() })
+    }
+}
+
+impl<'a, Trait> RefMut<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynAny,
+{
+    /// Returns `true` if the concrete type of this trait object is `T`.
+    pub fn is<T: 'static>(&self) -> bool {
+        self.as_ref().is::<T>()
+    }
+
+    /// Downcasts to the concrete type `T`, or `None` if it's not a `T`.
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.as_ref().downcast_ref()
+    }
+
+    /// Mutably downcasts to the concrete type `T`, or `None` if it's not a `T`.
+    pub fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        if !self.is::<T>() {
+            return None;
+        }
+        // SAFETY: This `RefMut` was built from a `&'a mut T`, as checked by the `TypeId` above,
+        // and the returned reference borrows `self` mutably.
+        Some(unsafe { self.inner.data.cast().as_mut() })
+    }
+
+    /// Converts into a mutable reference to the concrete type `T`.
+    ///
+    /// Returns `self` back if it's not a `T`.
+    pub fn into_downcast<T: 'static>(self) -> Result<&'a mut T, Self> {
+        if !self.is::<T>() {
+            return Err(self);
+        }
+        // SAFETY: This `RefMut` was built from a `&'a mut T`, as checked by the `TypeId` above,
+        // and `self` is consumed.
+        Ok(unsafe { self.inner.data.cast().as_mut() })
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Comparing tinydyn trait objects by their concrete value.
+
+use crate::__private::SelfPtr;
+use crate::{DynAny, DynTrait, Ref};
+
+/// A tinydyn trait object that can compare its concrete value for equality.
+///
+/// Implemented by `#[tinydyn(partial_eq)]` and `#[tinydyn(eq)]`, which require every implementer
+/// to be `'static` and implement [`PartialEq`] or [`Eq`] respectively.
+/// This makes [`Ref<dyn Trait>`] implement [`PartialEq`].
+///
+/// Two trait objects with different concrete types are never equal, as checked by [`DynAny`].
+///
+/// # Safety
+/// `eq` must call the [`PartialEq`] impl of the concrete type `meta` was built for.
+pub unsafe trait DynPartialEq: DynAny {
+    /// Compares two concrete values of the same type for equality.
+    ///
+    /// # Safety
+    /// `lhs` and `rhs` must both point to the concrete type that `meta` was built for.
+    #[doc(hidden)]
+    unsafe fn eq(
+        meta: Self::Metadata,
+        lhs: SelfPtr<*const Self>,
+        rhs: SelfPtr<*const Self>,
+    ) -> bool;
+}
+
+/// A tinydyn trait object whose concrete values implement [`Eq`].
+///
+/// Implemented by `#[tinydyn(eq)]`. This makes [`Ref<dyn Trait>`] implement [`Eq`].
+///
+/// # Safety
+/// Every concrete type that can build metadata for this trait must implement [`Eq`].
+pub unsafe trait DynEq: DynPartialEq {}
+
+impl<'a, 'b, Trait> PartialEq<Ref<'b, Trait>> for Ref<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynPartialEq,
+{
+    fn eq(&self, other: &Ref<'b, Trait>) -> bool {
+        let meta = self.inner.meta;
+        if <Trait::Plain as DynAny>::type_id(meta)
+            != <Trait::Plain as DynAny>::type_id(other.inner.meta)
+        {
+            return false;
+        }
+        // SAFETY: Both pointers were built from a reference to the same concrete type `meta`
+        // was built for, as they have the same `TypeId`.
+        unsafe {
+            <Trait::Plain as DynPartialEq>::eq(meta, self.inner.self_ref(), other.inner.self_ref())
+        }
+    }
+}
+
+impl<'a, Trait> Eq for Ref<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynEq,
+{
+}
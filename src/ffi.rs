@@ -0,0 +1,152 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Importing trait objects implemented in C.
+
+use core::ffi::c_void;
+use core::ptr::NonNull;
+
+use crate::{DynTrait, PlainDyn, Ref, RefMut};
+
+/// A tinydyn trait declared with `#[tinydyn(repr_c)]`.
+///
+/// Its vtable is a `repr(C)` struct of non-null `extern "C"` function pointers, one for each
+/// method in declaration order, which each take the `self` pointer first. So, C code can build a
+/// vtable for it, which can be imported with [`Ref::from_c`] and [`RefMut::from_c`].
+///
+/// # Safety
+/// `Self::VTable` must have the layout described above, and `Self::CVtable` must have the same
+/// layout, with each entry an ABI-compatible `Option` of its function pointer.
+pub unsafe trait ReprC: PlainDyn {
+    /// The vtable as C provides it, named `{Trait}CVtable`.
+    ///
+    /// Each entry may be null and takes raw pointers in place of references, like
+    /// `*mut c_void` for `&mut self`.
+    type CVtable: 'static;
+}
+
+/// A [`ReprC`] trait with a single `&self` method, which can be called from C as a callback.
+///
+/// # Safety
+/// `callback` must return the function in `meta`, which takes the data pointer as its first
+/// argument.
+pub unsafe trait CCallback: ReprC {
+    /// The method as an `unsafe extern "C" fn` that takes the data pointer as `*const c_void`.
+    type Callback: Copy;
+
+    /// Gets the callback function from `meta`.
+    #[doc(hidden)]
+    fn callback(meta: Self::Metadata) -> Self::Callback;
+}
+
+/// An entry of a `repr_c` vtable, as C may provide it.
+type CEntry = Option<unsafe extern "C" fn()>;
+
+/// Converts the C vtable of `Trait` into its metadata.
+///
+/// Returns `None` if any function pointer is null.
+fn c_metadata<Trait>(vtable: &'static Trait::CVtable) -> Option<Trait::Metadata>
+where
+    Trait: ?Sized + ReprC,
+{
+    const {
+        assert!(
+            size_of::<Trait::CVtable>() == size_of::<Trait::VTable>()
+                && align_of::<Trait::CVtable>() == align_of::<Trait::VTable>(),
+            "the C vtable has a different layout than the tinydyn vtable. \
+            This indicates a bug in tinydyn.",
+        );
+    }
+    let entries = size_of::<Trait::CVtable>() / size_of::<CEntry>();
+    let vtable: *const Trait::CVtable = vtable;
+    // SAFETY: A `ReprC` C vtable is an array of nullable function pointers.
+    let c_entries = unsafe { core::slice::from_raw_parts(vtable as *const CEntry, entries) };
+    if c_entries.iter().any(Option::is_none) {
+        return None;
+    }
+    // SAFETY: The C vtable has the same layout as the vtable, with ABI-compatible entries, and
+    // every entry is non-null.
+    let vtable = unsafe { &*(vtable as *const Trait::VTable) };
+    Some(Trait::metadata_from_vtable(vtable))
+}
+
+impl<'a, Trait: ?Sized + ReprC> Ref<'a, Trait> {
+    /// Wraps a C object, a context pointer and a struct of functions that take it, as a `Ref`.
+    ///
+    /// `vtable` is the generated `{Trait}CVtable`, whose functions take C types, like
+    /// `*const c_void` for `&self` or `*mut i32` for `&mut i32`. A vtable built in C from the
+    /// header of the trait can be cast to it.
+    ///
+    /// Returns `None` if any function pointer in `vtable` is null.
+    ///
+    /// # Safety
+    ///
+    /// - Calling each function with `ctx` must uphold the contract of its method for the
+    ///   lifetime `'a`, including the validity of anything it returns. `ctx` must not be
+    ///   mutated through during `'a`, unless the functions synchronize it themselves.
+    /// - If `Trait` has auto trait supertraits, like `Send`, the object must meet them.
+    pub unsafe fn from_c(ctx: NonNull<c_void>, vtable: &'static Trait::CVtable) -> Option<Self> {
+        let meta = c_metadata::<Trait>(vtable)?;
+        // SAFETY: The caller guarantees the validity of `ctx` with the functions in `vtable`.
+        Some(unsafe { Self::from_raw_parts(ctx.cast(), meta) })
+    }
+}
+
+impl<Trait> Ref<'static, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: CCallback,
+{
+    /// Splits this into a context pointer and a callback that calls the method of `Trait` on it.
+    ///
+    /// This suits C APIs that take a `void (*cb)(void *ctx, ...)` and a `void *ctx`.
+    /// The `'static` lifetime ensures the context outlives any later call from C.
+    /// Calling the callback with any other context is undefined behavior.
+    ///
+    /// ```ignore
+    /// #[tinydyn(repr_c)]
+    /// trait Tick {
+    ///     fn tick(&self, now: u32);
+    /// }
+    ///
+    /// static BLINKER: Blinker = Blinker::new();
+    /// let (ctx, cb) = Ref::<dyn Tick + Sync>::new(&BLINKER).into_c_callback();
+    /// unsafe { rtos_timer_register(cb, ctx.cast_mut()) };
+    /// ```
+    pub fn into_c_callback(self) -> (*const c_void, <Trait::Plain as CCallback>::Callback) {
+        let (data, meta) = self.to_raw_parts();
+        (
+            data.as_ptr() as *const c_void,
+            <Trait::Plain as CCallback>::callback(meta),
+        )
+    }
+}
+
+impl<'a, Trait: ?Sized + ReprC> RefMut<'a, Trait> {
+    /// Wraps a C object, a context pointer and a struct of functions that take it, as a `RefMut`.
+    ///
+    /// See [`Ref::from_c`].
+    ///
+    /// # Safety
+    ///
+    /// - Calling each function with `ctx` must uphold the contract of its method for the
+    ///   lifetime `'a`, including the validity of anything it returns. `ctx` must not be
+    ///   accessed by anything else during `'a`.
+    /// - If `Trait` has auto trait supertraits, like `Send`, the object must meet them.
+    pub unsafe fn from_c(ctx: NonNull<c_void>, vtable: &'static Trait::CVtable) -> Option<Self> {
+        let meta = c_metadata::<Trait>(vtable)?;
+        // SAFETY: The caller guarantees the validity of `ctx` with the functions in `vtable`.
+        Some(unsafe { Self::from_raw_parts(ctx.cast(), meta) })
+    }
+}
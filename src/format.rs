@@ -0,0 +1,94 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Formatting tinydyn trait objects with `Debug` and `Display` supertraits.
+
+use core::fmt;
+
+use crate::__private::DynTarget;
+use crate::{DynTrait, PlainDyn, Ref, RefMut};
+
+/// A tinydyn trait object whose trait has a [`Debug`](fmt::Debug) supertrait, like
+/// `trait Sensor: Debug`.
+///
+/// Implemented by `#[tinydyn]` and [`combine!`](crate::combine). The vtable formats the concrete
+/// value, so [`Ref<dyn Trait>`] prints it like a `&dyn Debug` would.
+///
+/// # Safety
+/// [`PlainDyn::fmt_debug`] must always return `Some`.
+pub unsafe trait DynDebug: PlainDyn {}
+
+/// A tinydyn trait object whose trait has a [`Display`](fmt::Display) supertrait, like
+/// `trait Sensor: Display`.
+///
+/// Implemented by `#[tinydyn]` and [`combine!`](crate::combine). This makes
+/// [`Ref<dyn Trait>`] implement [`Display`](fmt::Display) by formatting the concrete value.
+///
+/// # Safety
+/// [`PlainDyn::fmt_display`] must always return `Some`.
+pub unsafe trait DynDisplay: PlainDyn {}
+
+impl<Trait> fmt::Debug for DynTarget<Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynDebug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let meta = DynTarget::part_meta::<Trait::Plain>(self);
+        // SAFETY:
+        // - `self` points to the concrete type the metadata was built for.
+        // - `DynDebug` guarantees `fmt_debug` returns `Some`.
+        unsafe {
+            <Trait::Plain as PlainDyn>::fmt_debug(meta, DynTarget::self_ref(self), f)
+                .unwrap_unchecked()
+        }
+    }
+}
+
+impl<Trait> fmt::Display for DynTarget<Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynDisplay,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let meta = DynTarget::part_meta::<Trait::Plain>(self);
+        // SAFETY:
+        // - `self` points to the concrete type the metadata was built for.
+        // - `DynDisplay` guarantees `fmt_display` returns `Some`.
+        unsafe {
+            <Trait::Plain as PlainDyn>::fmt_display(meta, DynTarget::self_ref(self), f)
+                .unwrap_unchecked()
+        }
+    }
+}
+
+impl<Trait> fmt::Display for Ref<'_, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynDisplay,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<Trait> fmt::Display for RefMut<'_, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynDisplay,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
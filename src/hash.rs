@@ -0,0 +1,160 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hashing tinydyn trait objects by their concrete value.
+
+use core::hash::{Hash, Hasher};
+
+use crate::__private::SelfPtr;
+use crate::{tinydyn, DynTrait, PlainDyn, Ref, RefMut};
+
+/// A tinydyn-aware [`Hasher`], used to pass a hasher of any type through a vtable.
+///
+/// This is blanket implemented for every [`Hasher`], and [`RefMut<dyn DynHasher>`] implements
+/// [`Hasher`] by forwarding to the concrete hasher.
+/// The signed integer methods aren't included, as [`Hasher`] forwards them to the unsigned ones.
+#[tinydyn]
+pub trait DynHasher {
+    /// See [`Hasher::finish`].
+    fn finish(&self) -> u64;
+
+    /// See [`Hasher::write`].
+    fn write(&mut self, bytes: &[u8]);
+
+    /// See [`Hasher::write_u8`].
+    fn write_u8(&mut self, i: u8);
+
+    /// See [`Hasher::write_u16`].
+    fn write_u16(&mut self, i: u16);
+
+    /// See [`Hasher::write_u32`].
+    fn write_u32(&mut self, i: u32);
+
+    /// See [`Hasher::write_u64`].
+    fn write_u64(&mut self, i: u64);
+
+    /// See [`Hasher::write_u128`].
+    fn write_u128(&mut self, i: u128);
+
+    /// See [`Hasher::write_usize`].
+    fn write_usize(&mut self, i: usize);
+}
+
+impl<H: Hasher + ?Sized> DynHasher for H {
+    fn finish(&self) -> u64 {
+        Hasher::finish(self)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        Hasher::write(self, bytes)
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        Hasher::write_u8(self, i)
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        Hasher::write_u16(self, i)
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        Hasher::write_u32(self, i)
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        Hasher::write_u64(self, i)
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        Hasher::write_u128(self, i)
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        Hasher::write_usize(self, i)
+    }
+}
+
+impl<Trait> Hasher for RefMut<'_, Trait>
+where
+    Trait: ?Sized + DynTrait<Plain = dyn DynHasher>,
+{
+    fn finish(&self) -> u64 {
+        DynHasher::finish(&**self)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        DynHasher::write(&mut **self, bytes)
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        DynHasher::write_u8(&mut **self, i)
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        DynHasher::write_u16(&mut **self, i)
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        DynHasher::write_u32(&mut **self, i)
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        DynHasher::write_u64(&mut **self, i)
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        DynHasher::write_u128(&mut **self, i)
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        DynHasher::write_usize(&mut **self, i)
+    }
+}
+
+/// A tinydyn trait object that can hash its concrete value.
+///
+/// Implemented by `#[tinydyn(hash)]`, which requires every implementer to also implement
+/// [`Hash`]. This makes [`Ref<dyn Trait>`] implement [`Hash`].
+///
+/// # Safety
+/// `hash` must call the [`Hash`] impl of the concrete type `meta` was built for.
+pub unsafe trait DynHash: PlainDyn {
+    /// Hashes the concrete value `self_` points to.
+    ///
+    /// # Safety
+    /// `self_` must point to the concrete type that `meta` was built for.
+    #[doc(hidden)]
+    unsafe fn hash(
+        meta: Self::Metadata,
+        self_: SelfPtr<*const Self>,
+        state: RefMut<'_, dyn DynHasher>,
+    );
+}
+
+impl<'a, Trait> Hash for Ref<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynHash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // SAFETY: `self.inner` was built from a `&T` and the metadata for `T`.
+        unsafe {
+            <Trait::Plain as DynHash>::hash(
+                self.inner.meta,
+                self.inner.self_ref(),
+                RefMut::new(state),
+            )
+        }
+    }
+}
@@ -17,7 +17,7 @@
 //! [`Ref<dyn Trait>`] and [`RefMut<dyn Trait>`] wrap a pointer and metadata necessary to call
 //! trait methods, and [`Deref`] into a _tinydyn trait object_ that implements the `Trait`.
 //!
-//! Traits must currently opt-in by annotating with [`tinydyn`].
+//! Traits must currently opt-in by annotating with [`tinydyn`](macro@tinydyn).
 //! This defines an alternate, lighter weight [vtable], and if the trait has one method, eliminates
 //! it entirely by putting the function pointer inline.
 //! This does not affect normal behavior of the trait, and can still be made into a `dyn Trait`.
@@ -68,22 +68,28 @@
 //! - [x] lifetime generics on methods
 //! - [ ] implementing on foreign traits/custom vtables
 //! - [ ] implementations for common `core`/`std` traits
-//!       (never `core::fmt::{Debug, Display}` as they use `&dyn`)
+//!   (`core::fmt::{Debug, Display}` only as supertraits, as they use `&dyn`)
 //! - [ ] generics on the trait
 //! - [ ] associated types
 //! - [ ] supertraits
+//!     - [x] auto traits and lifetimes, like `trait Foo: Send + Sync + 'static`
+//!     - [x] `Debug` and `Display`, like `trait Foo: Debug`
 //!     - [ ] upcasting `Ref<dyn Subtrait>` to `Ref<dyn Supertrait>`
 //! - [ ] `Pin<&mut self>` and similar non-reference object-safe receivers
 //! - [ ] `where` bounds on the trait
 //! - [ ] `where Self: Sized` methods (and appropriate exclusion from the vtable)
 //!     - [ ] non-lifetime generics on methods
-//!     - [ ] non-lifetime `where` bounds on methods
+//!     - [ ] non-lifetime `where` bounds on methods, other than `where Self: Sync`
 //!     - [ ] An attribute to manually exclude a method from a vtable, necessary for bounds
-//!           including subtraits or aliases of `Sized`
+//!       including subtraits or aliases of `Sized`
 //! - [ ] An `tinydyn(inline_vtable[ = "all"])` attribute to force inlining of the vtable into the
-//!       wide pointer. This would require the metadata type to always be carried in the trait.
+//!   wide pointer. This would require the metadata type to always be carried in the trait.
 //! - [ ] Put `Ref` vtables inline even if `RefMut` won't. Ex: 1 `&self` and 1 `&mut self` method.
 //! - [ ] UI tests to ensure proper rejection and error message quality
+//! - [ ] `DynTarget` as a custom DST whose pointer metadata is the tinydyn metadata, making
+//!   `Ref` a plain `&DynTarget`. This is blocked on Rust itself: even on nightly, `ptr_metadata`
+//!   only exposes the built-in metadata of slices, `str` and `dyn Trait`, and `Pointee` can't be
+//!   implemented for a type with custom metadata.
 //!
 //! ### Implementing on foreign traits
 //!
@@ -93,7 +99,7 @@
 //! blanket implements for all `T: TargetTrait`.
 //! This functionality might be added by tinydyn in the future, or a better solution like defining
 //! custom local vtables for foreign traits.
-//! Traits with supertraits that wish to use this version of `tinydyn` have a similar workaround.
+//! Traits with other supertraits that wish to use this version of `tinydyn` have a similar workaround.
 //!
 //! ## Design
 //!
@@ -104,6 +110,10 @@
 #![warn(unsafe_op_in_unsafe_fn)]
 #![warn(missing_docs)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::fmt;
 use core::marker::PhantomData;
 
 use core::ops::{Deref, DerefMut};
@@ -116,10 +126,56 @@ use core::ptr::NonNull;
 #[path = "private.rs"]
 pub mod __private;
 
+mod addr;
+mod any;
+mod cmp;
+mod ffi;
+mod format;
+mod hash;
+mod marker;
+mod optional;
+#[cfg(feature = "std")]
+pub mod plugin;
+mod query;
+mod stable_abi;
+mod std_dyn;
+mod vtable;
+
+pub use addr::ByAddress;
+pub use any::DynAny;
+pub use cmp::{DynEq, DynPartialEq};
+pub use ffi::{CCallback, ReprC};
+pub use format::{DynDebug, DynDisplay};
+pub use hash::{DynHash, DynHasher};
+pub use marker::{Marked, RemoveMarker};
+pub use optional::Overrides;
+pub use query::{DynQuery, Provided, Provides};
+pub use stable_abi::{AbiHeader, LayoutError, StableAbi, VtableLayout};
+pub use std_dyn::StdDyn;
+pub use vtable::VTableFor;
+
+// Lets `#[tinydyn]` be used on traits inside of this crate.
+extern crate self as tinydyn;
+
 /// Marks a local trait as tinydyn-aware, letting it be used inside of [`Ref`] and [`RefMut`].
 ///
 /// This implements [`DynTrait`] and [`PlainDyn`] for the targeted trait object.
 /// This defines an alternate smaller vtable layout that erases layout and drop information.
+/// That vtable type is named `{Trait}Vtable`, with the same visibility as `Trait`.
+/// Get one for a concrete type with [`vtable!`].
+///
+/// `{Trait}Vtable::builder` returns a `{Trait}VtableBuilder`, which assembles a [`VTableFor`]
+/// from functions instead of a trait impl. It has a setter named after each method,
+/// except one named `build`, whose setter is `set_build`.
+/// Setters take function pointers, so only non-capturing closures can be passed. The erased
+/// context is the `Concrete` the `Ref` points to, which they receive as `self`: a vtable is
+/// shared by every object it's used with, so it has nowhere to keep captured state.
+///
+/// The trait may have auto trait and lifetime supertraits, like `trait Foo: Send + Sync + 'static`.
+/// Like `dyn Foo`, `Ref<dyn Foo>` then implements those auto traits without spelling them out.
+/// It may also have [`Debug`](core::fmt::Debug) and [`Display`](core::fmt::Display)
+/// supertraits, which add an entry to the vtable that formats the concrete value. Then
+/// [`Ref<dyn Foo>`] prints the value with `{:?}` or `{}`. See [`DynDebug`] and [`DynDisplay`].
 ///
 /// While you *can* use tinydyn-aware traits as regular `dyn Trait` trait objects, it's not
 /// recommended as it creates two vtables.
@@ -145,9 +201,162 @@ pub mod __private;
 /// assert_eq!(x.blah(), 16);
 /// assert_eq!(x.blue(), 10);
 /// ```
+///
+/// # Options
+///
+/// Options are passed as a comma-separated list, like `#[tinydyn(eq, hash)]`.
+/// Each adds entries to the vtable, so only enable what's needed.
+///
+/// - `downcast`: [`Ref<dyn Trait>`] and [`RefMut<dyn Trait>`] can be checked and downcast to their
+///   concrete type with [`Ref::downcast_ref`] and [`RefMut::downcast_mut`].
+///   Every implementer must be `'static`, as [`DynAny`] explains.
+/// - `partial_eq`: [`Ref<dyn Trait>`] implements [`PartialEq`] by comparing the concrete values.
+///   Values with different concrete types are never equal.
+///   Every implementer must be `'static` and implement [`PartialEq`]. See [`DynPartialEq`].
+/// - `eq`: like `partial_eq`, but implementers must implement [`Eq`], and so does `Ref`.
+/// - `hash`: [`Ref<dyn Trait>`] implements [`Hash`](core::hash::Hash) by hashing the concrete
+///   value. Every implementer must implement `Hash`. See [`DynHash`].
+/// - `markers(..)`: extra marker traits the trait object can carry, like
+///   `markers(Unpin, UnwindSafe, my::IsrSafe)`. `Send` and `Sync` are always supported.
+///   The auto traits `Unpin`, `UnwindSafe` and `RefUnwindSafe` are added with `+`, like
+///   `dyn Trait + Send + Unpin`. Other traits are attached with [`Marked`], like
+///   `Marked<dyn Trait + Send, dyn IsrSafe>`. Each auto trait doubles the number of generated
+///   impls.
+/// - `query`: [`Ref<dyn Trait>`] can be queried for other tinydyn traits its concrete type
+///   provides with [`Ref::query`]. Every impl of the trait must list them with
+///   [`#[tinydyn::provides(..)]`](macro@provides). See [`DynQuery`].
+/// - `ref_impls`: [`RefMut<dyn Trait>`] implements `Trait`, and so does [`Ref<dyn Trait>`] if
+///   every method takes `&self`. They can then be passed by value to generic code, and
+///   unoptimized method calls on them skip the double pointer from `Deref`. Without this
+///   option, and for a `Ref` to a trait with `&mut self` methods, method calls go through
+///   `Deref`, which only optimized builds compile down to a direct vtable call. This is opt-in
+///   because it conflicts with a blanket impl over a bound `Ref` also meets, like
+///   `impl<T: Debug> Trait for T`, which tinydyn can't detect. Without it, such a blanket impl
+///   also covers `Ref` itself, so `r.method()` picks that impl over the trait object; call
+///   `(*r).method()` instead. Can't be combined with a `'static` supertrait.
+/// - `repr_c`: `{Trait}Vtable` is `repr(C)`, and each entry is an `extern "C"` function that
+///   takes the erased `self` pointer first. `{Trait}Vtable::C_HEADER` declares the vtable and
+///   [`Ref`] and [`RefMut`] for C. Objects implemented in C can be imported with
+///   [`Ref::from_c`], from a `{Trait}CVtable` of nullable functions that take raw pointers
+///   instead of references. See [`ReprC`]. A trait with a single `&self` method also implements
+///   [`CCallback`], for [`Ref::into_c_callback`]. This can't be combined with other options.
+///   Arguments and return values are limited to primitives other than `char`, `u128` and
+///   `i128`, the C types in [`core::ffi`], and pointers, references and `NonNull`s to those.
+///   A type named by a path, like `core::ffi::c_int`, must spell out its module. Methods can't
+///   be named after C keywords like `default` or `register`.
+/// - `stable_abi(version = N, min_version = M)`: like `repr_c`, but for sharing vtables between
+///   separately built images. The vtable starts with an [`AbiHeader`] holding its size and
+///   version `N`. Every method must be marked `#[tinydyn(since = K)]`, and new methods can only
+///   be added at the end. [`Ref::try_from_versioned`] rejects vtables older than `M`, which
+///   defaults to 1. Methods added after `M` must have a default body, which runs if an older
+///   vtable is missing them. They're also callable through the `try_` methods of a generated
+///   `{Trait}Versioned` trait, which return `None` if they're missing. See
+///   [`StableAbi`], and [`VtableLayout`] for checking that the methods match. With the `std`
+///   feature, `tinydyn::plugin` loads these objects from shared libraries.
+///
+/// # Method options
+///
+/// - `#[tinydyn(optional)]` on a provided method: its vtable entry is an `Option`, which is only
+///   filled in if the concrete type overrides the method. Otherwise, calling it through a [`Ref`]
+///   runs the default body. The generated `{Trait}Optional` trait has a `has_{method}` query
+///   for each optional method. Every impl of the trait must be marked with
+///   [`#[tinydyn::overrides]`](macro@overrides), which records the methods it overrides.
+/// - `#[tinydyn(since = K)]`: the version a method of a `stable_abi` trait was added in.
+///
+/// A method may also require auto traits of `Self`, like `fn spawn(&self) where Self: Sync`.
+/// It's only callable through a trait object marked with them, like `Ref<dyn Trait + Sync>`,
+/// whose vtable is the only one that fills in its entry. Auto traits other than `Send` and
+/// `Sync` must be listed in `markers(..)`. So, metadata passed to [`Ref::from_raw_parts`] must
+/// come from a trait object with the same markers.
+///
+/// # std trait objects
+///
+/// `dyn Trait` implements [`StdDyn`] unless the trait has the `repr_c`, `stable_abi`,
+/// `partial_eq`, `eq` or `hash` options, or `where Self: ..` methods. A `&dyn Trait` can then be
+/// wrapped with [`Ref::from_std_dyn`], and [`Ref::as_std_dyn`] returns a generated
+/// `{Trait}Std` adapter that can be coerced to a `&dyn Trait`. For a trait with `&mut self`
+/// methods, only the adapter from [`RefMut::as_std_dyn`] implements the trait.
 pub use tinydyn_derive::tinydyn;
 
-use __private::DynTarget;
+/// Records which methods an impl of a tinydyn trait overrides, implementing [`Overrides`].
+///
+/// This is required on every impl of a trait with `#[tinydyn(optional)]` methods.
+///
+/// ```ignore
+/// #[tinydyn::overrides]
+/// impl Uart for Pl011 {
+///     fn write(&mut self, byte: u8) { /* ... */ }
+///     fn set_baud(&mut self, baud: u32) -> bool { /* ... */ }
+/// }
+/// ```
+pub use tinydyn_derive::overrides;
+
+/// Declares a trait combining two or more tinydyn traits, usable as [`Ref<dyn Combined>`].
+///
+/// Rust only allows one non-auto trait in a trait object, so `Ref<dyn Sensor + Configurable>`
+/// isn't possible. Instead, this declares `Combined` as a subtrait of each, implemented for every
+/// type that implements all of them. Its vtable holds the vtable of each trait, so its trait
+/// object can call the methods of all of them.
+///
+/// ```ignore
+/// tinydyn::combine!(pub trait SensorAndConfig = Sensor + Configurable);
+///
+/// fn calibrate(device: RefMut<dyn SensorAndConfig>) {
+///     let offset = device.read();
+///     device.set_offset(offset);
+/// }
+/// ```
+pub use tinydyn_derive::combine;
+
+/// Lists the other tinydyn traits a type provides to a `#[tinydyn(query)]` trait, implementing
+/// [`Provides`].
+///
+/// This is required on every impl of a trait with the `query` option, though the list may be
+/// empty. Each listed trait must be implemented by the type.
+///
+/// ```ignore
+/// #[tinydyn::provides(dyn Dimmable, dyn PowerManaged)]
+/// impl Device for Lamp {
+///     fn name(&self) -> &str { "lamp" }
+/// }
+///
+/// let device: Ref<dyn Device> = Ref::new(&lamp);
+/// if let Some(dimmable) = device.query::<dyn Dimmable>() {
+///     dimmable.set_level(50);
+/// }
+/// ```
+pub use tinydyn_derive::provides;
+
+/// Gets the `&'static` [`VTable`] of a tinydyn trait for a concrete type.
+///
+/// This is written `vtable!(dyn Trait for Type)`, and expands to a call to [`vtable_for`].
+///
+/// ```ignore
+/// static FOO_FOR_BAR: &FooVtable = tinydyn::vtable!(dyn Foo for Bar);
+/// ```
+pub use tinydyn_derive::vtable;
+
+use __private::{DynTarget, SelfPtr};
+
+/// The vtable type generated for a tinydyn trait, named through its trait object.
+///
+/// This is the same type as the `{Trait}Vtable` that `#[tinydyn]` defines alongside `Trait`.
+/// It implements [`PartialEq`] and [`Debug`](core::fmt::Debug), comparing and printing its
+/// function pointers.
+pub type VTable<Trait> = <<Trait as DynTrait>::Plain as PlainDyn>::VTable;
+
+/// Gets the `&'static` [`VTable`] of `Trait` for the concrete type `U`.
+///
+/// See also [`vtable!`]. The returned reference may or may not be the same one that [`Ref::new`]
+/// uses, and the compiler may duplicate or merge vtables, so compare vtables by value rather than
+/// address.
+pub const fn vtable_for<Trait, U>() -> &'static VTable<Trait>
+where
+    Trait: ?Sized + DynTrait,
+    LocalWrap<Trait, U>: Implements<Trait>,
+{
+    <LocalWrap<Trait, U> as Implements<Trait>>::MARKED_VTABLE_REF
+}
 
 /// Wraps `T` with the local newtype associated with this tinydyn trait.
 ///
@@ -159,8 +368,19 @@ type LocalWrap<Trait, T> = <<Trait as DynTrait>::Plain as PlainDyn>::LocalNewtyp
 /// `Ref<dyn Trait>` can call the `&self` methods of `Trait` through its `Deref` impl.
 /// It can also be freely cloned and copied like a `&dyn Trait`.
 ///
+/// With the `ref_impls` option, if every method of `Trait` takes `&self`, `Ref<dyn Trait>` also
+/// implements `Trait`, so it can be passed by value to generic code like `fn f(x: impl Trait)`.
+/// Method calls then call the vtable directly, while for other traits they go through `Deref`,
+/// which unoptimized builds pay for as a double pointer.
+///
 /// Prefer passing this around rather than calling `deref` and passing around that reference
 /// - that would create a double pointer.
+///
+/// # Layout
+///
+/// This is `repr(C)`: a data pointer followed by the metadata, which is either a pointer to the
+/// vtable or an inline vtable. With `#[tinydyn(repr_c)]`, this matches the `{Trait}Ref` in the
+/// trait's generated C header.
 #[repr(C)]
 pub struct Ref<'a, Trait: ?Sized + DynTrait> {
     inner: DynPtr<'a, Trait>,
@@ -170,10 +390,7 @@ pub struct Ref<'a, Trait: ?Sized + DynTrait> {
 impl<'a, Trait: ?Sized + DynTrait + 'a> Copy for Ref<'a, Trait> {}
 impl<'a, Trait: ?Sized + DynTrait + 'a> Clone for Ref<'a, Trait> {
     fn clone(&self) -> Self {
-        Self {
-            inner: self.inner.clone(),
-            _lifetime: PhantomData,
-        }
+        *self
     }
 }
 
@@ -182,6 +399,7 @@ impl<'a, Trait: ?Sized + DynTrait + 'a> Deref for Ref<'a, Trait> {
 
     /// It's not recommended to hold onto the result of this `deref`, as it creates a
     /// double reference.
+    #[inline(always)]
     fn deref(&self) -> &Self::Target {
         self.inner.deref()
     }
@@ -191,12 +409,21 @@ impl<'a, Trait: ?Sized + DynTrait> Ref<'a, Trait> {
     /// Upcasts this `&U` into a `Ref<dyn Trait>` so long as `U: Trait`.
     ///
     /// This builds a tinydyn vtable and references it in the returned `Ref`.
-    pub fn new<U>(r: &'a U) -> Self
+    ///
+    /// This is a `const fn`, so it can build dispatch tables in a `static`:
+    ///
+    /// ```ignore
+    /// static DRIVERS: [Ref<'static, dyn Driver + Sync>; 2] = [Ref::new(&Uart), Ref::new(&Spi)];
+    /// ```
+    pub const fn new<U>(r: &'a U) -> Self
     where
         LocalWrap<Trait, U>: Implements<Trait>,
     {
-        let data = NonNull::from(r).cast();
-        let meta = <LocalWrap<Trait, U> as BuildDynMeta<Trait::Plain>>::metadata();
+        // SAFETY: A reference is never null.
+        let data = unsafe { NonNull::new_unchecked(r as *const U as *mut U) }.cast();
+        let meta = <LocalWrap<Trait, U> as Implements<Trait>>::MARKED_METADATA;
+        // SAFETY: `meta` is the vtable of `U` for `Trait` with its markers, and `data` comes
+        // from a `&'a U`.
         let inner = unsafe { DynPtr::new(data, meta) };
         Self {
             inner,
@@ -204,25 +431,45 @@ impl<'a, Trait: ?Sized + DynTrait> Ref<'a, Trait> {
         }
     }
 
-    unsafe fn from_inner(inner: DynPtr<'a, Trait>) -> Self {
+    const unsafe fn from_inner(inner: DynPtr<'a, Trait>) -> Self {
         Self {
             inner,
             _lifetime: PhantomData,
         }
     }
-}
 
-impl<'a, Trait: ?Sized + DynTrait + Send + 'a> Ref<'a, Trait> {
-    /// Removes the `Send` bound from `Trait`, if any.
-    pub fn remove_send(self) -> Ref<'a, Trait::RemoveSend> {
-        unsafe { Ref::from_inner(self.inner.remove_send()) }
+    /// Builds a `Ref` from a data pointer and the metadata for its concrete type.
+    ///
+    /// This is the inverse of [`Ref::to_raw_parts`].
+    ///
+    /// # Safety
+    ///
+    /// For some concrete type `T`:
+    /// - `data` must be valid to convert to a `&'a T`.
+    /// - `meta` must have been built for `T`, such as by [`Ref::to_raw_parts`] or
+    ///   [`RefMut::into_raw_parts`] on a trait object made from a `T`.
+    /// - `T` must be able to cast to `Trait`, including its `Send` and `Sync` bounds.
+    /// - `meta` must have been built for `Trait` including its markers, like `Sync` or those
+    ///   from [`Marked`]. Those fill in the entries of `where Self: Sync` methods, so metadata
+    ///   from a `dyn Foo` can't be used for a `dyn Foo + Sync`.
+    pub const unsafe fn from_raw_parts(
+        data: NonNull<()>,
+        meta: <Trait::Plain as PlainDyn>::Metadata,
+    ) -> Self {
+        // SAFETY: The caller upholds the requirements for a `Ref`.
+        unsafe { Self::from_inner(DynPtr::new(data, meta)) }
     }
-}
 
-impl<'a, Trait: ?Sized + DynTrait + Sync + 'a> Ref<'a, Trait> {
-    /// Removes the `Sync` bound from `Trait`, if any.
-    pub fn remove_sync(self) -> Ref<'a, Trait::RemoveSync> {
-        unsafe { Ref::from_inner(self.inner.remove_sync()) }
+    /// Splits this `Ref` into its data pointer and metadata.
+    ///
+    /// These can be stored separately and rejoined with [`Ref::from_raw_parts`].
+    pub fn to_raw_parts(self) -> (NonNull<()>, <Trait::Plain as PlainDyn>::Metadata) {
+        (self.inner.data, self.inner.meta)
+    }
+
+    /// Gets the pointer metadata for this trait object.
+    pub fn metadata(&self) -> <Trait::Plain as PlainDyn>::Metadata {
+        self.inner.meta
     }
 }
 
@@ -253,15 +500,21 @@ where
 /// A mutable reference to a tinydyn trait object.
 ///
 /// `RefMut<dyn Trait>` can call the `&self` and `&mut self` methods of `Trait` through its
-/// `Deref` impl.
+/// `Deref` impl. With the `ref_impls` option, it also implements `Trait`.
 ///
 /// Like `&mut dyn Trait`, this cannot be cloned or copied. It can, however, be [reborrowed].
 ///
 /// Prefer passing this around rather than calling `deref_mut` and passing around that reference
 /// - that would create a double pointer.
 ///
+/// # Layout
+///
+/// This is `repr(C)`: a data pointer followed by the metadata, which is either a pointer to the
+/// vtable or an inline vtable. With `#[tinydyn(repr_c)]`, this matches the `{Trait}RefMut` in the
+/// trait's generated C header.
+///
 /// [reborrowed]: RefMut::as_mut
-#[repr(transparent)]
+#[repr(C)]
 pub struct RefMut<'a, Trait: ?Sized + DynTrait> {
     inner: DynPtr<'a, Trait>,
     _lifetime: PhantomData<&'a mut Trait>,
@@ -272,6 +525,7 @@ impl<'a, Trait: ?Sized + DynTrait + 'a> Deref for RefMut<'a, Trait> {
 
     /// It's not recommended to hold onto the result of this `deref`, as it creates a
     /// double reference.
+    #[inline(always)]
     fn deref(&self) -> &Self::Target {
         self.inner.deref()
     }
@@ -280,6 +534,7 @@ impl<'a, Trait: ?Sized + DynTrait + 'a> Deref for RefMut<'a, Trait> {
 impl<'a, Trait: ?Sized + DynTrait + 'a> DerefMut for RefMut<'a, Trait> {
     /// It's not recommended to hold onto the result of this `deref`, as it creates a
     /// double reference.
+    #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.inner.deref_mut()
     }
@@ -289,12 +544,15 @@ impl<'a, Trait: ?Sized + DynTrait> RefMut<'a, Trait> {
     /// Upcasts this `&mut U` into a `RefMut<dyn Trait>` so long as `U: Trait`.
     ///
     /// This builds a tinydyn vtable and references it in the returned `RefMut`.
-    pub fn new<U>(r: &'a mut U) -> Self
+    pub const fn new<U>(r: &'a mut U) -> Self
     where
         LocalWrap<Trait, U>: Implements<Trait>,
     {
-        let data = NonNull::from(r).cast();
-        let meta = <LocalWrap<Trait, U> as BuildDynMeta<Trait::Plain>>::metadata();
+        // SAFETY: A reference is never null.
+        let data = unsafe { NonNull::new_unchecked(r as *mut U) }.cast();
+        let meta = <LocalWrap<Trait, U> as Implements<Trait>>::MARKED_METADATA;
+        // SAFETY: `meta` is the vtable of `U` for `Trait` with its markers, and `data` comes
+        // from a `&'a mut U`.
         let inner = unsafe { DynPtr::new(data, meta) };
         Self {
             inner,
@@ -308,13 +566,45 @@ impl<'a, Trait: ?Sized + DynTrait + 'a> RefMut<'a, Trait> {
     ///
     /// # Safety
     /// The `inner` pointer must be safe to mutate through.
-    unsafe fn from_inner(inner: DynPtr<'a, Trait>) -> Self {
+    const unsafe fn from_inner(inner: DynPtr<'a, Trait>) -> Self {
         Self {
             inner,
             _lifetime: PhantomData,
         }
     }
 
+    /// Builds a `RefMut` from a data pointer and the metadata for its concrete type.
+    ///
+    /// This is the inverse of [`RefMut::into_raw_parts`].
+    ///
+    /// # Safety
+    ///
+    /// For some concrete type `T`:
+    /// - `data` must be valid to convert to a `&'a mut T`.
+    /// - `meta` must have been built for `T`, such as by [`Ref::to_raw_parts`] or
+    ///   [`RefMut::into_raw_parts`] on a trait object made from a `T`.
+    /// - `T` must be able to cast to `Trait`, including its `Send` and `Sync` bounds.
+    /// - `meta` must have been built for `Trait` including its markers, like `Sync` or those
+    ///   from [`Marked`]. Those fill in the entries of `where Self: Sync` methods, so metadata
+    ///   from a `dyn Foo` can't be used for a `dyn Foo + Sync`.
+    /// - `meta` must not come from a `Ref` made by [`Ref::from_std_dyn`], whose vtable can't
+    ///   call `&mut self` methods.
+    pub const unsafe fn from_raw_parts(
+        data: NonNull<()>,
+        meta: <Trait::Plain as PlainDyn>::Metadata,
+    ) -> Self {
+        // SAFETY: The caller upholds the requirements for a `RefMut`.
+        unsafe { Self::from_inner(DynPtr::new(data, meta)) }
+    }
+
+    /// Splits this `RefMut` into its data pointer and metadata.
+    ///
+    /// The data pointer can be mutated through for `'a`.
+    /// These can be rejoined with [`RefMut::from_raw_parts`].
+    pub fn into_raw_parts(self) -> (NonNull<()>, <Trait::Plain as PlainDyn>::Metadata) {
+        (self.inner.data, self.inner.meta)
+    }
+
     /// Reborrow as a shared `Ref` with a smaller lifetime.
     ///
     /// Since a `RefMut` isn't `Copy`, this is needed to pass to a function expecting a `Ref` and
@@ -338,6 +628,7 @@ impl<'a, Trait: ?Sized + DynTrait + 'a> RefMut<'a, Trait> {
     ///
     /// The pointer can accessed soundly for the lifetime of `&mut self`
     /// so long as `self` was constructed from a `&mut T`.
+    /// This is unchecked; prefer [`RefMut::downcast_mut`] for traits with `#[tinydyn(downcast)]`.
     pub fn as_downcast_ptr<T>(&mut self) -> NonNull<T> {
         self.inner.data.cast()
     }
@@ -348,20 +639,6 @@ impl<'a, Trait: ?Sized + DynTrait + 'a> RefMut<'a, Trait> {
     }
 }
 
-impl<'a, Trait: ?Sized + DynTrait + Send + 'a> RefMut<'a, Trait> {
-    /// Removes the `Send` bound from `Trait`, if any.
-    pub fn remove_send(self) -> RefMut<'a, Trait::RemoveSend> {
-        unsafe { RefMut::from_inner(self.inner.remove_send()) }
-    }
-}
-
-impl<'a, Trait: ?Sized + DynTrait + Sync + 'a> RefMut<'a, Trait> {
-    /// Removes the `Sync` bound from `Trait`, if any.
-    pub fn remove_sync(self) -> RefMut<'a, Trait::RemoveSync> {
-        unsafe { RefMut::from_inner(self.inner.remove_sync()) }
-    }
-}
-
 unsafe impl<'a, Trait> Send for RefMut<'a, Trait>
 where
     Trait: ?Sized + DynTrait,
@@ -377,7 +654,13 @@ where
 }
 
 /// The shared inner pointer of [`Ref`] and [`RefMut`].
+///
+/// This is `repr(C)` so `Ref` and `RefMut` have a layout C can use.
+#[repr(C)]
 pub(crate) struct DynPtr<'a, Trait: ?Sized + DynTrait> {
+    /// Cast from the `&T` or `&mut T` the object was made from, keeping its provenance.
+    /// Reborrows like [`RefMut::as_mut`] copy this pointer rather than deriving a new one,
+    /// so it stays valid under Stacked and Tree Borrows until `'a` ends.
     data: NonNull<()>,
     meta: <Trait::Plain as PlainDyn>::Metadata,
     _lifetime: PhantomData<&'a ()>,
@@ -400,16 +683,12 @@ where
 impl<'a, Trait: ?Sized + DynTrait + 'a> Copy for DynPtr<'a, Trait> {}
 impl<'a, Trait: ?Sized + DynTrait + 'a> Clone for DynPtr<'a, Trait> {
     fn clone(&self) -> Self {
-        Self {
-            data: self.data.clone(),
-            meta: self.meta.clone(),
-            _lifetime: PhantomData,
-        }
+        *self
     }
 }
 
 impl<'a, Trait: ?Sized + DynTrait + 'a> DynPtr<'a, Trait> {
-    pub(crate) unsafe fn new(
+    pub(crate) const unsafe fn new(
         data: NonNull<()>,
         meta: <Trait::Plain as PlainDyn>::Metadata,
     ) -> Self {
@@ -421,20 +700,18 @@ impl<'a, Trait: ?Sized + DynTrait + 'a> DynPtr<'a, Trait> {
     }
 }
 
-impl<'a, Trait: ?Sized + DynTrait + Send + 'a> DynPtr<'a, Trait> {
-    /// Removes the `Send` bound from `Trait`, if any.
-    pub fn remove_send(self) -> DynPtr<'a, Trait::RemoveSend> {
-        DynPtr {
-            data: self.data,
-            meta: self.meta,
-            _lifetime: PhantomData,
-        }
+impl<'a, Trait: ?Sized + DynTrait + 'a> DynPtr<'a, Trait> {
+    pub(crate) fn self_ref(&self) -> SelfPtr<*const Trait::Plain> {
+        SelfPtr::new_ref(self.data)
     }
 }
 
-impl<'a, Trait: ?Sized + DynTrait + Sync + 'a> DynPtr<'a, Trait> {
-    /// Removes the `Sync` bound from `Trait`, if any.
-    pub fn remove_sync(self) -> DynPtr<'a, Trait::RemoveSync> {
+impl<'a, Trait: ?Sized + DynTrait + 'a> DynPtr<'a, Trait> {
+    /// Changes the markers of `Trait`, keeping the same plain trait object.
+    pub(crate) fn with_markers<Other>(self) -> DynPtr<'a, Other>
+    where
+        Other: ?Sized + DynTrait<Plain = Trait::Plain>,
+    {
         DynPtr {
             data: self.data,
             meta: self.meta,
@@ -446,12 +723,14 @@ impl<'a, Trait: ?Sized + DynTrait + Sync + 'a> DynPtr<'a, Trait> {
 impl<'a, Trait: ?Sized + DynTrait + 'a> Deref for DynPtr<'a, Trait> {
     type Target = DynTarget<Trait>;
 
+    #[inline(always)]
     fn deref(&self) -> &Self::Target {
         DynTarget::new_ref(self)
     }
 }
 
 impl<'a, Trait: ?Sized + DynTrait + 'a> DerefMut for DynPtr<'a, Trait> {
+    #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
         DynTarget::new_mut(self)
     }
@@ -468,6 +747,12 @@ pub unsafe trait PlainDyn: DynTrait<Plain = Self> {
     /// The vtable duplicated for each combination of trait and concrete type.
     type StaticVTable: Copy;
 
+    /// The full vtable for this trait, the generated `{Trait}Vtable`.
+    ///
+    /// This is nameable as [`VTable<dyn Trait>`], and a `&'static` one for a concrete type is
+    /// returned by [`vtable!`].
+    type VTable: Copy + 'static;
+
     /// A local generic transparent newtype.
     ///
     /// Used to work around the [coherence] rules by tying in a local newtype.
@@ -482,27 +767,63 @@ pub unsafe trait PlainDyn: DynTrait<Plain = Self> {
     ///
     /// [coherence]: https://github.com/rust-lang/rfcs/blob/master/text/2451-re-rebalancing-coherence.md
     type LocalNewtype<T>;
+
+    /// Converts a vtable into the metadata carried alongside a `Ref` and `RefMut`.
+    ///
+    /// Combined with [`Ref::from_raw_parts`], this lets one vtable be shared by many data pointers.
+    fn metadata_from_vtable(vtable: &'static Self::VTable) -> Self::Metadata;
+
+    /// Gets an address identifying the vtable in `meta`.
+    ///
+    /// For an inline vtable, this is the address of its function, if any.
+    #[doc(hidden)]
+    fn vtable_addr(meta: Self::Metadata) -> *const ();
+
+    /// Gets the name of the concrete type `meta` was built for, if the `type_name` feature is on.
+    /// Inline and `repr_c` vtables don't record it.
+    #[doc(hidden)]
+    fn type_name(meta: Self::Metadata) -> Option<&'static str>;
+
+    /// Formats `self_` with its [`Debug`](fmt::Debug) impl, or returns `None` if the trait
+    /// doesn't have a `Debug` supertrait.
+    ///
+    /// # Safety
+    /// `self_` must point to the concrete type that `meta` was built for.
+    #[doc(hidden)]
+    unsafe fn fmt_debug(
+        meta: Self::Metadata,
+        self_: SelfPtr<*const Self>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> Option<fmt::Result>;
+
+    /// Formats `self_` with its [`Display`](fmt::Display) impl, or returns `None` if the trait
+    /// doesn't have a `Display` supertrait.
+    ///
+    /// # Safety
+    /// `self_` must point to the concrete type that `meta` was built for.
+    #[doc(hidden)]
+    unsafe fn fmt_display(
+        meta: Self::Metadata,
+        self_: SelfPtr<*const Self>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> Option<fmt::Result>;
 }
 
 /// A trait object that works with `tinydyn`, including any extra bounds.
 ///
-/// `dyn Trait` erases that a trait object implements `Send` or `Sync`, so
-/// Rust allows a concrete type that implements either of those traits to
-/// cast to a `dyn Trait [+ Send][+ Sync]` object.
+/// `dyn Trait` erases that a trait object implements marker traits like `Send`, so
+/// Rust allows a concrete type that implements them to cast to a `dyn Trait + Send` object.
+/// `#[tinydyn]` implements this for `dyn Trait` with every combination of `Send`, `Sync`, and
+/// the markers listed in its `markers(..)` option. Other marker traits are attached with
+/// [`Marked`]. Markers are removed with [`Ref::remove_marker`] and [`Ref::remove_all_markers`].
 ///
-/// `tinydyn` itself doesn't depend on `Send` or `Sync` the important code.
+/// `tinydyn` itself doesn't depend on the markers in the important code.
 ///
 /// # Safety
-/// The associated types must be correct as described.
+/// `Plain` must be the trait object without any extra bounds.
 pub unsafe trait DynTrait {
     /// The trait object without any extra bounds.
     type Plain: PlainDyn + ?Sized;
-
-    /// The trait object without the `+ Send` bound. If it has none, this must be `Self`.
-    type RemoveSend: DynTrait<Plain = Self::Plain> + ?Sized;
-
-    /// The trait object without the `+ Sync` bound. If it has none, this must be `Self`.
-    type RemoveSync: DynTrait<Plain = Self::Plain> + ?Sized;
 }
 
 /// Builds the tinydyn trait metadata for a given type.
@@ -510,25 +831,55 @@ pub unsafe trait DynTrait {
 /// This metadata is shared by `dyn Trait [+ Send] [+ Sync]`.
 ///
 /// Implemented for `LocalNewtype<T>` where `T` implements the `Trait`.
+///
+/// # Safety
+/// The metadata must be valid to call the trait methods with a pointer to `Self`.
 pub unsafe trait BuildDynMeta<Trait>
 where
     Self: Sized,
     Trait: PlainDyn + ?Sized,
 {
+    /// The full vtable for this type.
+    const VTABLE: Trait::VTable;
+
+    /// A `&'static` reference to [`Self::VTABLE`].
+    const VTABLE_REF: &'static Trait::VTable;
+
     /// The contents of the vtable for this type.
     /// If the metadata is a function pointer, this is unused.
     const STATIC_VTABLE: Trait::StaticVTable;
 
+    /// The pointer metadata necessary to call trait methods.
+    ///
+    /// This is either a reference to [`Self::STATIC_VTABLE`] or an inline vtable.
+    const METADATA: Trait::Metadata;
+
     /// Gets the pointer metadata necessary to call trait methods.
-    fn metadata() -> Trait::Metadata;
+    fn metadata() -> Trait::Metadata {
+        Self::METADATA
+    }
 }
 
 /// Types that could be cast to the given `Trait` trait object.
 ///
 /// Implemented by `LocalNewtype<T>` where `T` implements the `Trait`.
+///
+/// # Safety
+/// `Self` must be able to soundly cast to `Trait`, including any extra bounds.
+/// The marked vtable must be valid to call the trait methods with a pointer to `Self`.
 pub unsafe trait Implements<Trait>
 where
     Self: BuildDynMeta<Trait::Plain>,
     Trait: DynTrait + ?Sized,
 {
+    /// The vtable used when casting to `Trait`, which also fills in the methods its markers
+    /// enable, like `where Self: Sync` methods for `dyn Trait + Sync`.
+    #[doc(hidden)]
+    const MARKED_VTABLE_REF: &'static VTable<Trait> =
+        <Self as BuildDynMeta<Trait::Plain>>::VTABLE_REF;
+
+    /// The metadata for [`Self::MARKED_VTABLE_REF`].
+    #[doc(hidden)]
+    const MARKED_METADATA: <Trait::Plain as PlainDyn>::Metadata =
+        <Self as BuildDynMeta<Trait::Plain>>::METADATA;
 }
@@ -0,0 +1,97 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Marker traits carried by a tinydyn trait object, like `dyn Trait + Send`.
+
+use core::marker::PhantomData;
+
+use crate::{DynTrait, Ref, RefMut};
+
+/// A tinydyn trait object that carries the marker trait `M`, which can be removed.
+///
+/// `#[tinydyn]` implements this for each auto trait in `dyn Trait + Send + ...`, with `M` as
+/// `dyn Send`. [`Marked`] implements it for the marker it attaches.
+///
+/// # Safety
+/// `Output` must be `Self` without `M`.
+pub unsafe trait RemoveMarker<M: ?Sized>: DynTrait {
+    /// The trait object without `M`.
+    type Output: ?Sized + DynTrait<Plain = Self::Plain>;
+}
+
+/// The trait object `Trait`, whose concrete type also implements the marker trait `M`.
+///
+/// Rust only allows auto traits like `Send` to be added to a trait object with `+`. This
+/// attaches any other marker trait listed in the `markers(..)` option of `#[tinydyn]`, written
+/// as a trait object:
+///
+/// ```ignore
+/// #[tinydyn(markers(IsrSafe))]
+/// trait Handler {
+///     fn handle(&self);
+/// }
+///
+/// fn register(handler: Ref<'static, Marked<dyn Handler + Sync, dyn IsrSafe>>) { /* ... */ }
+/// ```
+///
+/// Markers can be nested, like `Marked<Marked<dyn Handler, dyn IsrSafe>, dyn Pinned>`.
+/// This is never constructed; it only names a trait object in [`Ref`] and [`RefMut`].
+pub struct Marked<Trait: ?Sized, M: ?Sized> {
+    _marker: PhantomData<fn(&M)>,
+    _trait: Trait,
+}
+
+// SAFETY: The marker doesn't change the plain trait object.
+unsafe impl<Trait: ?Sized + DynTrait, M: ?Sized> DynTrait for Marked<Trait, M> {
+    type Plain = Trait::Plain;
+}
+
+// SAFETY: `Trait` is `Self` without `M`.
+unsafe impl<Trait: ?Sized + DynTrait, M: ?Sized> RemoveMarker<M> for Marked<Trait, M> {
+    type Output = Trait;
+}
+
+impl<'a, Trait: ?Sized + DynTrait> Ref<'a, Trait> {
+    /// Removes the marker trait `M`, like `remove_marker::<dyn Send>()`.
+    pub fn remove_marker<M: ?Sized>(self) -> Ref<'a, Trait::Output>
+    where
+        Trait: RemoveMarker<M>,
+    {
+        // SAFETY: The concrete type still implements the plain trait.
+        unsafe { Ref::from_inner(self.inner.with_markers()) }
+    }
+
+    /// Removes every marker trait, leaving the plain `dyn Trait`.
+    pub fn remove_all_markers(self) -> Ref<'a, Trait::Plain> {
+        // SAFETY: The concrete type still implements the plain trait.
+        unsafe { Ref::from_inner(self.inner.with_markers()) }
+    }
+}
+
+impl<'a, Trait: ?Sized + DynTrait> RefMut<'a, Trait> {
+    /// Removes the marker trait `M`, like `remove_marker::<dyn Send>()`.
+    pub fn remove_marker<M: ?Sized>(self) -> RefMut<'a, Trait::Output>
+    where
+        Trait: RemoveMarker<M>,
+    {
+        // SAFETY: The concrete type still implements the plain trait.
+        unsafe { RefMut::from_inner(self.inner.with_markers()) }
+    }
+
+    /// Removes every marker trait, leaving the plain `dyn Trait`.
+    pub fn remove_all_markers(self) -> RefMut<'a, Trait::Plain> {
+        // SAFETY: The concrete type still implements the plain trait.
+        unsafe { RefMut::from_inner(self.inner.with_markers()) }
+    }
+}
@@ -0,0 +1,29 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording which methods an impl overrides, for `#[tinydyn(optional)]` methods.
+
+/// Lists the provided methods of `Trait` that an impl overrides.
+///
+/// A trait with `#[tinydyn(optional)]` methods only fills in their vtable entries for the
+/// methods a concrete type overrides, so every impl of it must be marked with
+/// [`#[tinydyn::overrides]`](macro@crate::overrides), which implements this.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't record which methods of `{Trait}` it overrides",
+    note = "mark the impl of the trait for `{Self}` with `#[tinydyn::overrides]`"
+)]
+pub trait Overrides<Trait: ?Sized> {
+    /// The names of the methods the impl defines.
+    const OVERRIDDEN: &'static [&'static str];
+}
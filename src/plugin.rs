@@ -0,0 +1,162 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loading trait objects from shared libraries.
+//!
+//! A plugin is a shared library that exports an `extern "C" fn() -> PluginDescriptor` for each
+//! object it provides. The object's trait must be declared with
+//! `#[tinydyn(stable_abi(..))]`, so that the host and plugin can be built separately:
+//!
+//! ```ignore
+//! // In the plugin, a `cdylib`:
+//! #[no_mangle]
+//! pub extern "C" fn english_greeter() -> PluginDescriptor {
+//!     PluginDescriptor::new::<dyn Greeter>(Ref::new(&English))
+//! }
+//!
+//! // In the host:
+//! let plugin = unsafe { Plugin::open("libgreeters.so")? };
+//! let greeter: Ref<dyn Greeter> = unsafe { plugin.get("english_greeter")? };
+//! ```
+//!
+//! The host checks the descriptor's [`VtableLayout`] against its own build of the trait before
+//! handing back the object.
+
+use core::ffi::c_void;
+use core::fmt;
+use core::ptr::NonNull;
+use std::ffi::OsStr;
+
+use crate::{LayoutError, Ref, StableAbi, VtableLayout};
+
+/// An object exported by a plugin, with the layout of its vtable.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PluginDescriptor {
+    /// The version of this struct, [`PluginDescriptor::VERSION`].
+    pub descriptor_version: u32,
+    /// The layout of `vtable`.
+    pub layout: VtableLayout,
+    /// The object, passed to each method in `vtable`.
+    pub ctx: *const c_void,
+    /// The stable vtable of the object.
+    pub vtable: *const c_void,
+}
+
+impl PluginDescriptor {
+    /// The version of the descriptor layout, bumped if its fields ever change.
+    pub const VERSION: u32 = 1;
+
+    /// Describes an object to export from a plugin.
+    pub fn new<Trait: ?Sized + StableAbi>(object: Ref<'static, Trait>) -> Self {
+        let (ctx, meta) = object.to_raw_parts();
+        Self {
+            descriptor_version: Self::VERSION,
+            layout: VtableLayout::of::<Trait>(),
+            ctx: ctx.as_ptr() as *const c_void,
+            vtable: Trait::vtable_ptr(meta).as_ptr() as *const c_void,
+        }
+    }
+}
+
+/// A loaded plugin, which stays loaded as long as the objects taken from it.
+#[derive(Debug)]
+pub struct Plugin {
+    library: libloading::Library,
+}
+
+impl Plugin {
+    /// Loads the shared library at `path`.
+    ///
+    /// # Safety
+    /// Loading a library runs its initialization routines, which must be safe to run.
+    /// See [`libloading::Library::new`].
+    pub unsafe fn open(path: impl AsRef<OsStr>) -> Result<Self, PluginError> {
+        // SAFETY: The caller guarantees the library can be loaded.
+        let library = unsafe { libloading::Library::new(path) }.map_err(PluginError::Load)?;
+        Ok(Self { library })
+    }
+
+    /// Gets the object exported as `symbol`, after checking its vtable against `Trait`.
+    ///
+    /// The vtable may be older or newer than this build of `Trait`, as with
+    /// [`Ref::try_from_versioned`].
+    ///
+    /// # Safety
+    ///
+    /// - `symbol` must be an `extern "C" fn() -> PluginDescriptor`, returning a descriptor
+    ///   built with [`PluginDescriptor::new`] or with the same guarantees.
+    /// - The fingerprints only check the names and C types of the methods. Each method the
+    ///   two builds of the trait share must also have the same contract.
+    /// - If `Trait` has auto trait supertraits, like `Send`, the exported object must meet them.
+    pub unsafe fn get<Trait: ?Sized + StableAbi>(
+        &self,
+        symbol: &str,
+    ) -> Result<Ref<'_, Trait>, PluginError> {
+        // SAFETY: The caller guarantees the type of the symbol.
+        let descriptor = unsafe {
+            let export = self
+                .library
+                .get::<extern "C" fn() -> PluginDescriptor>(symbol.as_bytes())
+                .map_err(PluginError::Load)?;
+            export()
+        };
+        if descriptor.descriptor_version != PluginDescriptor::VERSION {
+            return Err(PluginError::InvalidDescriptor);
+        }
+        // SAFETY: The caller guarantees the descriptor describes its vtable.
+        unsafe { descriptor.layout.check::<Trait>() }.map_err(PluginError::Layout)?;
+        let (Some(ctx), Some(vtable)) = (
+            NonNull::new(descriptor.ctx as *mut c_void),
+            NonNull::new(descriptor.vtable as *mut Trait::VTable),
+        ) else {
+            return Err(PluginError::InvalidDescriptor);
+        };
+        // SAFETY: The vtable was checked to have the methods of `Trait`, and the object lives
+        // as long as the library is loaded.
+        unsafe { Ref::try_from_versioned(ctx, vtable) }.ok_or(PluginError::InvalidDescriptor)
+    }
+}
+
+/// Why an object couldn't be loaded from a [`Plugin`].
+#[derive(Debug)]
+pub enum PluginError {
+    /// The library or symbol couldn't be loaded.
+    Load(libloading::Error),
+    /// The descriptor has an unknown version, a null pointer, or a header that disagrees
+    /// with its layout.
+    InvalidDescriptor,
+    /// The vtable doesn't match this build of the trait.
+    Layout(LayoutError),
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(err) => write!(f, "couldn't load plugin: {err}"),
+            Self::InvalidDescriptor => f.write_str("invalid plugin descriptor"),
+            Self::Layout(err) => write!(f, "plugin vtable mismatch: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Load(err) => Some(err),
+            Self::InvalidDescriptor => None,
+            Self::Layout(err) => Some(err),
+        }
+    }
+}
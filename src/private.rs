@@ -27,6 +27,14 @@ use crate::{BuildDynMeta, DynTrait, PlainDyn};
 #[repr(transparent)]
 pub struct SelfPtr<TraitPtr>(NonNull<()>, PhantomData<TraitPtr>);
 
+// A shared `self` can be copied like a `&T`.
+impl<Trait: ?Sized> Copy for SelfPtr<*const Trait> {}
+impl<Trait: ?Sized> Clone for SelfPtr<*const Trait> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
 impl<Trait: ?Sized + PlainDyn> SelfPtr<*const Trait> {
     pub(crate) fn new_ref(self_: NonNull<()>) -> Self {
         Self(self_, PhantomData)
@@ -40,6 +48,15 @@ impl<Trait: ?Sized + PlainDyn> SelfPtr<*const Trait> {
         unsafe { self.0.cast().as_ref() }
     }
 
+    /// Points to the same object as a part of `Trait`, like one trait of a `combine!`d trait.
+    pub fn part<Part>(self) -> SelfPtr<*const Part>
+    where
+        Trait: HasPart<Part>,
+        Part: ?Sized + PlainDyn,
+    {
+        SelfPtr(self.0, PhantomData)
+    }
+
     pub fn upcast<Super>(self) -> SelfPtr<*const Super>
     where
         Trait: Implements<Super>,
@@ -73,7 +90,7 @@ impl<Trait: ?Sized + PlainDyn> SelfPtr<*mut Trait> {
 /// This serves two purposes:
 ///
 /// - `DynTarget` is not `Sized`, so it's stopped from calling `where Self: Sized` trait
-///    functions at compile time.
+///   functions at compile time.
 /// - You cannot soundly swap an unowned unsized object, so the lifetime of `Self` doesn't have to
 ///   reflect the lifetime of the trait object.
 ///
@@ -105,8 +122,9 @@ pub struct DynTarget<Trait: ?Sized + DynTrait> {
     ptr: DynPtr<'static, Trait>,
     _phantom: PhantomData<Trait>,
 
-    /// A slice whose only purpose is to carry a length.
-    /// This length is the dynamic metadata, function pointer or static vtable.
+    /// A slice whose only purpose is to make `DynTarget` unsized.
+    /// Its length is always 0: the metadata lives in `ptr`, as Rust has no custom pointer
+    /// metadata, even with the nightly `ptr_metadata` feature.
     ///
     /// Since it is a 1-aligned ZST, it does not affect the layout of the type.
     /// So, since `DynTarget` is `repr(C)`, it has the same size/align as `data`.
@@ -120,9 +138,12 @@ impl<Trait: ?Sized + DynTrait> DynTarget<Trait> {
         // SAFETY:
         // - The pointer cast preserves the pointer metadata of a 0 slice length.
         // - `make_unsized` has size 0 and alignment 1, meaning `DynTarget<Trait>` has the same
-        //   layout as `DynPtr<'_, Trait>`.
-        // - The data portion of `wide_slice` retains provenance for a `DynPtr<'_, Trait>`
-        //   as an intermediate reference with smaller provenance was never formed.
+        //   layout as `DynPtr<'_, Trait>`. So the new reference covers exactly the bytes of
+        //   `ptr`, and retagging it under Stacked or Tree Borrows stays within `ptr`'s borrow.
+        // - `wide_slice` is derived from `ptr` by pointer casts alone, never through an integer,
+        //   so it keeps the provenance of `ptr` under strict provenance.
+        // - The data pointer inside is only ever copied out, not reborrowed, so it keeps the
+        //   provenance of the `&T` or `&mut T` the object was made from.
         // - The lifetime of `ptr` is never exposed, and there is no legal way to swap two
         //   unowned unsized types.
         unsafe { &*(wide_slice as *const Self) }
@@ -132,13 +153,14 @@ impl<Trait: ?Sized + DynTrait> DynTarget<Trait> {
     pub(crate) fn new_mut<'a, 'b: 'a>(ptr: &'a mut DynPtr<'b, Trait>) -> &'a mut Self {
         let wide_slice: *mut [DynPtr<'_, Trait>] = core::ptr::slice_from_raw_parts_mut(ptr, 0);
         // SAFETY:
-        // - See `Self::new`
+        // - See `Self::new_ref`. The unique borrow of `ptr` is reborrowed, and covers the same
+        //   bytes.
         unsafe { &mut *(wide_slice as *mut Self) }
     }
 
     #[inline(always)]
     pub fn self_ref(self_: &Self) -> SelfPtr<*const Trait::Plain> {
-        SelfPtr::new_ref(self_.ptr.data)
+        self_.ptr.self_ref()
     }
 
     #[inline(always)]
@@ -150,6 +172,137 @@ impl<Trait: ?Sized + DynTrait> DynTarget<Trait> {
     pub fn meta(self_: &Self) -> <Trait::Plain as PlainDyn>::Metadata {
         self_.ptr.meta
     }
+
+    /// Gets the metadata of `Part`, one of the traits `Trait` is made of.
+    #[inline(always)]
+    pub fn part_meta<Part>(self_: &Self) -> Part::Metadata
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        <Trait::Plain as HasPart<Part>>::part(self_.ptr.meta)
+    }
+
+    #[inline(always)]
+    pub fn part_ref<Part>(self_: &Self) -> SelfPtr<*const Part>
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        SelfPtr::new_ref(self_.ptr.data)
+    }
+
+    #[inline(always)]
+    pub fn part_mut<Part>(self_: &mut Self) -> SelfPtr<*mut Part>
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        SelfPtr::new_mut(self_.ptr.data)
+    }
+}
+
+/// A [`Ref`] or [`RefMut`], whose trait impls call the vtable with the pointer it holds.
+///
+/// Unlike going through `Deref` to a `DynTarget`, this doesn't create a double pointer that only
+/// optimizations remove.
+///
+/// [`Ref`]: crate::Ref
+/// [`RefMut`]: crate::RefMut
+pub trait DynHandle {
+    type Trait: ?Sized + DynTrait;
+
+    /// Gets the metadata of `Part`, one of the traits `Trait` is made of.
+    fn part_meta<Part>(self_: &Self) -> Part::Metadata
+    where
+        Part: ?Sized + PlainDyn,
+        <Self::Trait as DynTrait>::Plain: HasPart<Part>;
+
+    fn part_ref<Part>(self_: &Self) -> SelfPtr<*const Part>
+    where
+        Part: ?Sized + PlainDyn,
+        <Self::Trait as DynTrait>::Plain: HasPart<Part>;
+}
+
+/// A [`RefMut`], which can also call `&mut self` methods.
+///
+/// [`RefMut`]: crate::RefMut
+pub trait DynHandleMut: DynHandle {
+    fn part_mut<Part>(self_: &mut Self) -> SelfPtr<*mut Part>
+    where
+        Part: ?Sized + PlainDyn,
+        <Self::Trait as DynTrait>::Plain: HasPart<Part>;
+}
+
+impl<Trait: ?Sized + DynTrait> DynHandle for crate::Ref<'_, Trait> {
+    type Trait = Trait;
+
+    #[inline(always)]
+    fn part_meta<Part>(self_: &Self) -> Part::Metadata
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        <Trait::Plain as HasPart<Part>>::part(self_.inner.meta)
+    }
+
+    #[inline(always)]
+    fn part_ref<Part>(self_: &Self) -> SelfPtr<*const Part>
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        SelfPtr::new_ref(self_.inner.data)
+    }
+}
+
+impl<Trait: ?Sized + DynTrait> DynHandle for crate::RefMut<'_, Trait> {
+    type Trait = Trait;
+
+    #[inline(always)]
+    fn part_meta<Part>(self_: &Self) -> Part::Metadata
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        <Trait::Plain as HasPart<Part>>::part(self_.inner.meta)
+    }
+
+    #[inline(always)]
+    fn part_ref<Part>(self_: &Self) -> SelfPtr<*const Part>
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        SelfPtr::new_ref(self_.inner.data)
+    }
+}
+
+impl<Trait: ?Sized + DynTrait> DynHandleMut for crate::RefMut<'_, Trait> {
+    #[inline(always)]
+    fn part_mut<Part>(self_: &mut Self) -> SelfPtr<*mut Part>
+    where
+        Part: ?Sized + PlainDyn,
+        Trait::Plain: HasPart<Part>,
+    {
+        SelfPtr::new_mut(self_.inner.data)
+    }
+}
+
+/// A trait object that `Part`'s methods can be called on, like a `combine!`d trait.
+///
+/// # Safety
+/// `part` must return metadata for the same concrete type as `meta`.
+pub unsafe trait HasPart<Part: ?Sized + PlainDyn>: PlainDyn {
+    fn part(meta: Self::Metadata) -> Part::Metadata;
+}
+
+// SAFETY: A trait object is made of itself.
+unsafe impl<Trait: ?Sized + PlainDyn> HasPart<Trait> for Trait {
+    #[inline(always)]
+    fn part(meta: Self::Metadata) -> Self::Metadata {
+        meta
+    }
 }
 
 /// The [`PlainDyn::StaticVTable`] for traits without a vtable.
@@ -159,18 +312,205 @@ impl<Trait: ?Sized + DynTrait> DynTarget<Trait> {
 #[derive(Clone, Copy)]
 pub struct InlineVTable;
 
-/// Unsafely `transmute` from `Src` to `Dst` with a transmute check at runtime,
-/// and not compile time.
+/// Unsafely `transmute` from `Src` to `Dst`, which must have the same layout.
 ///
-/// The panic *should* always be compiled out. If it isn't, something's wrong.
+/// The layouts are compared at compile time, when each `Src` and `Dst` pair is instantiated,
+/// so this has no panic path even at `opt-level=0`. `transmute` itself can't be used on
+/// generic types.
 #[inline(always)]
-pub unsafe fn runtime_layout_verified_transmute<Src, Dst>(src: Src) -> Dst {
-    assert!(
-        core::alloc::Layout::new::<Src>() == core::alloc::Layout::new::<Dst>(),
-        "Bare argument layout mismatch. This indicates a bug in tinydyn."
-    );
-    let src_manual_drop = core::mem::ManuallyDrop::new(src);
-    let dst =
-        unsafe { core::mem::transmute_copy::<core::mem::ManuallyDrop<Src>, Dst>(&src_manual_drop) };
-    dst
+pub unsafe fn layout_verified_transmute<Src, Dst>(src: Src) -> Dst {
+    const {
+        assert!(
+            core::mem::size_of::<Src>() == core::mem::size_of::<Dst>()
+                && core::mem::align_of::<Src>() == core::mem::align_of::<Dst>(),
+            "Bare argument layout mismatch. This indicates a bug in tinydyn."
+        );
+    }
+    #[repr(C)]
+    union Transmute<Src, Dst> {
+        src: core::mem::ManuallyDrop<Src>,
+        dst: core::mem::ManuallyDrop<Dst>,
+    }
+    // SAFETY: `Src` and `Dst` have the same layout, and the caller guarantees the bytes of `src`
+    // are a valid `Dst`.
+    unsafe {
+        core::mem::ManuallyDrop::into_inner(
+            Transmute::<Src, Dst> {
+                src: core::mem::ManuallyDrop::new(src),
+            }
+            .dst,
+        )
+    }
+}
+
+/// Whether `T` overrides `method` of `Trait`. Used to fill the vtable for
+/// `#[tinydyn(optional)]`.
+pub const fn overrides<Trait: ?Sized, T: crate::Overrides<Trait>>(method: &str) -> bool {
+    let mut i = 0;
+    while i < T::OVERRIDDEN.len() {
+        if str_eq(T::OVERRIDDEN[i], method) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Hashes `self_` into `state`. Used to fill the vtable for `#[tinydyn(hash)]`.
+pub fn hash_thunk<T: core::hash::Hash>(
+    self_: &T,
+    mut state: crate::RefMut<'_, dyn crate::DynHasher>,
+) {
+    self_.hash(&mut state)
+}
+
+/// Formats `self_` with `Debug`. Used to fill the vtable for a `Debug` supertrait.
+pub fn debug_thunk<T: core::fmt::Debug>(
+    self_: &T,
+    f: &mut core::fmt::Formatter<'_>,
+) -> core::fmt::Result {
+    core::fmt::Debug::fmt(self_, f)
+}
+
+/// Formats `self_` with `Display`. Used to fill the vtable for a `Display` supertrait.
+pub fn display_thunk<T: core::fmt::Display>(
+    self_: &T,
+    f: &mut core::fmt::Formatter<'_>,
+) -> core::fmt::Result {
+    core::fmt::Display::fmt(self_, f)
+}
+
+/// A reference to a std trait object that the `StdDyn` vtables call methods through.
+pub trait StdPtr: core::ops::Deref {
+    /// Gets the target mutably.
+    ///
+    /// # Safety
+    /// `self` must be a `&mut`. The vtable for a shared reference is only used by a `Ref`, which
+    /// can't call `&mut self` methods.
+    unsafe fn get_mut(&mut self) -> &mut Self::Target;
+}
+
+impl<T: ?Sized> StdPtr for &T {
+    #[inline(always)]
+    unsafe fn get_mut(&mut self) -> &mut T {
+        // SAFETY: The caller guarantees this isn't a shared reference.
+        unsafe { core::hint::unreachable_unchecked() }
+    }
+}
+
+impl<T: ?Sized> StdPtr for &mut T {
+    #[inline(always)]
+    unsafe fn get_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+/// Never constructed. Its `TypeId` is given to wrapped std trait objects, so they can't be
+/// downcast.
+pub struct StdObject(());
+
+/// The name of the concrete type a vtable was built for, if the `type_name` feature is enabled.
+///
+/// Otherwise, this is zero-sized and doesn't grow the vtable.
+#[derive(Clone, Copy)]
+pub struct TypeName {
+    #[cfg(feature = "type_name")]
+    name: fn() -> &'static str,
+}
+
+impl TypeName {
+    pub const fn of<T: ?Sized>() -> Self {
+        Self {
+            #[cfg(feature = "type_name")]
+            name: core::any::type_name::<T>,
+        }
+    }
+
+    #[cfg(feature = "type_name")]
+    #[inline(always)]
+    pub fn get(self) -> Option<&'static str> {
+        Some((self.name)())
+    }
+
+    #[cfg(not(feature = "type_name"))]
+    #[inline(always)]
+    pub fn get(self) -> Option<&'static str> {
+        None
+    }
+}
+
+impl PartialEq for TypeName {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl Eq for TypeName {}
+
+impl core::fmt::Debug for TypeName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+/// The metadata of a `stable_abi` vtable, which may be older and smaller than its type.
+///
+/// This never creates a reference to the whole vtable, so only its header and the entries
+/// within its size are accessed.
+pub struct VtablePtr<V>(NonNull<V>);
+
+impl<V> Clone for VtablePtr<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<V> Copy for VtablePtr<V> {}
+
+// SAFETY: A vtable is immutable.
+unsafe impl<V> Send for VtablePtr<V> {}
+unsafe impl<V> Sync for VtablePtr<V> {}
+
+impl<V> VtablePtr<V> {
+    pub const fn new(vtable: &'static V) -> Self {
+        // SAFETY: A reference is never null.
+        Self(unsafe { NonNull::new_unchecked(vtable as *const V as *mut V) })
+    }
+
+    /// `vtable` must point to a stable vtable, which starts with an `AbiHeader`.
+    pub const unsafe fn from_ptr(vtable: NonNull<V>) -> Self {
+        Self(vtable)
+    }
+
+    #[inline(always)]
+    pub const fn as_non_null(self) -> NonNull<V> {
+        self.0
+    }
+
+    #[inline(always)]
+    pub fn as_ptr(self) -> *const V {
+        self.0.as_ptr()
+    }
+
+    /// Whether the vtable holds at least `end` bytes, according to its header.
+    #[inline(always)]
+    pub fn covers(self, end: usize) -> bool {
+        // SAFETY: Every stable vtable starts with a header.
+        let header = unsafe { self.0.cast::<crate::AbiHeader>().read() };
+        header.size as usize >= end
+    }
 }
@@ -0,0 +1,145 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! COM-style querying of a tinydyn trait object for other traits its concrete type provides.
+
+use core::any::TypeId;
+use core::fmt;
+use core::ptr::NonNull;
+
+use crate::{DynTrait, Implements, PlainDyn, Ref, RefMut};
+
+/// A tinydyn trait object whose vtable lists other traits its concrete type provides.
+///
+/// Implemented by `#[tinydyn(query)]`, which requires every impl to be marked with
+/// [`#[tinydyn::provides(..)]`](macro@crate::provides).
+/// This enables [`Ref::query`] and [`RefMut::query_mut`].
+///
+/// # Safety
+/// `provided` must return the table of the concrete type `meta` was built for.
+pub unsafe trait DynQuery: PlainDyn {
+    /// Gets the traits provided by the concrete type this metadata was built for.
+    #[doc(hidden)]
+    fn provided(meta: Self::Metadata) -> &'static [Provided];
+}
+
+/// Lists the tinydyn traits, other than `Trait`, that a `Ref<dyn Trait>` can be queried for.
+///
+/// Implemented by [`#[tinydyn::provides(..)]`](macro@crate::provides) on an impl of `Trait`.
+///
+/// # Safety
+/// Every entry of `PROVIDED` must be built for `Self`, with [`Provided::new::<_, Self>`].
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't list which traits it provides to `{Trait}`",
+    note = "mark the impl of the trait for `{Self}` with `#[tinydyn::provides(..)]`"
+)]
+pub unsafe trait Provides<Trait: ?Sized> {
+    /// The vtables of the provided traits for `Self`.
+    const PROVIDED: &'static [Provided];
+}
+
+/// The vtable of a tinydyn trait for some concrete type, keyed by the trait.
+#[derive(Clone, Copy)]
+pub struct Provided {
+    trait_id: fn() -> TypeId,
+    /// The `&'static Trait::VTable`.
+    vtable: NonNull<()>,
+}
+
+impl Provided {
+    /// The vtable of `Trait` for `T`.
+    pub const fn new<Trait, T>() -> Self
+    where
+        Trait: ?Sized + PlainDyn + 'static,
+        Trait::LocalNewtype<T>: Implements<Trait>,
+    {
+        let vtable: &'static Trait::VTable = crate::vtable_for::<Trait, T>();
+        Self {
+            trait_id: TypeId::of::<Trait>,
+            // SAFETY: A reference is never null.
+            vtable: unsafe { NonNull::new_unchecked(vtable as *const _ as *mut ()) },
+        }
+    }
+}
+
+// SAFETY: The vtable is `'static` and immutable.
+unsafe impl Send for Provided {}
+unsafe impl Sync for Provided {}
+
+impl fmt::Debug for Provided {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Provided")
+            .field("trait_id", &(self.trait_id)())
+            .field("vtable", &self.vtable)
+            .finish()
+    }
+}
+
+/// Finds the vtable of `Other` in the table of the concrete type of `meta`.
+fn find<Trait, Other>(meta: <Trait::Plain as PlainDyn>::Metadata) -> Option<Other::Metadata>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynQuery,
+    Other: ?Sized + PlainDyn + 'static,
+{
+    let provided = <Trait::Plain as DynQuery>::provided(meta)
+        .iter()
+        .find(|provided| (provided.trait_id)() == TypeId::of::<Other>())?;
+    // SAFETY: The vtable was built for `Other` by `Provided::new`, as checked by the `TypeId`.
+    let vtable = unsafe { provided.vtable.cast::<Other::VTable>().as_ref() };
+    Some(Other::metadata_from_vtable(vtable))
+}
+
+impl<'a, Trait> Ref<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynQuery,
+{
+    /// Returns `true` if the concrete type of this trait object provides `Other`.
+    pub fn provides<Other: ?Sized + PlainDyn + 'static>(&self) -> bool {
+        find::<Trait, Other>(self.inner.meta).is_some()
+    }
+
+    /// Gets this object as `dyn Other`, or `None` if its concrete type doesn't provide it.
+    pub fn query<Other: ?Sized + PlainDyn + 'static>(&self) -> Option<Ref<'a, Other>> {
+        let meta = find::<Trait, Other>(self.inner.meta)?;
+        // SAFETY: The concrete type implements `Other`, and `meta` was built for it.
+        Some(unsafe { Ref::from_raw_parts(self.inner.data, meta) })
+    }
+}
+
+impl<'a, Trait> RefMut<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: DynQuery,
+{
+    /// Returns `true` if the concrete type of this trait object provides `Other`.
+    pub fn provides<Other: ?Sized + PlainDyn + 'static>(&self) -> bool {
+        self.as_ref().provides::<Other>()
+    }
+
+    /// Gets this object as `dyn Other`, or `None` if its concrete type doesn't provide it.
+    pub fn query<Other: ?Sized + PlainDyn + 'static>(&self) -> Option<Ref<'_, Other>> {
+        self.as_ref().query()
+    }
+
+    /// Mutably gets this object as `dyn Other`, or `None` if its concrete type doesn't provide
+    /// it.
+    pub fn query_mut<Other: ?Sized + PlainDyn + 'static>(&mut self) -> Option<RefMut<'_, Other>> {
+        let meta = find::<Trait, Other>(self.inner.meta)?;
+        // SAFETY: The concrete type implements `Other`, `meta` was built for it, and the
+        // returned reference borrows `self` mutably.
+        Some(unsafe { RefMut::from_raw_parts(self.inner.data, meta) })
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Versioned vtables with a stable layout, for sharing between separately built images.
+
+use core::ffi::c_void;
+use core::fmt;
+use core::ptr::NonNull;
+
+use crate::{PlainDyn, Ref, RefMut};
+
+/// The start of every `#[tinydyn(stable_abi(..))]` vtable.
+///
+/// Methods are only ever appended to a stable vtable, so its size says which methods it has.
+/// The C header of the trait declares it as `TinydynAbiHeader`, in a field named `header`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AbiHeader {
+    /// The size of the whole vtable in bytes, including this header.
+    pub size: u32,
+    /// The version of the trait the vtable was built for.
+    pub version: u32,
+}
+
+/// A tinydyn trait declared with `#[tinydyn(stable_abi(version = N))]`.
+///
+/// Its vtable is a `repr(C)` struct that starts with an [`AbiHeader`], followed by an
+/// `extern "C"` function pointer for each method in declaration order. Each method is marked
+/// with the version it was added in, and methods are only added at the end.
+///
+/// # Safety
+/// `Self::VTable` must have the layout described above, and the constants must describe it.
+pub unsafe trait StableAbi: PlainDyn {
+    /// The version of the vtables built for this trait.
+    const VERSION: u32;
+
+    /// The oldest version of a vtable that can be imported.
+    const MIN_VERSION: u32;
+
+    /// The size of a vtable of [`Self::MIN_VERSION`], which has every required method.
+    const MIN_SIZE: usize;
+
+    /// A fingerprint of the name and C signature of each method, in vtable order.
+    ///
+    /// See [`VtableLayout`].
+    const SIGNATURES: &'static [u64];
+
+    /// Gets the vtable that `meta` points to.
+    #[doc(hidden)]
+    fn vtable_ptr(meta: Self::Metadata) -> NonNull<Self::VTable>;
+
+    /// Gets the metadata for a vtable that may be older than [`Self::VERSION`].
+    ///
+    /// # Safety
+    /// `vtable` must point to a vtable whose header describes it, for the `'static` lifetime.
+    #[doc(hidden)]
+    unsafe fn metadata_from_ptr(vtable: NonNull<Self::VTable>) -> Self::Metadata;
+}
+
+/// Describes a stable vtable, for checking it against another build of its trait.
+///
+/// The header of a vtable says how many methods it has, but not whether they are the methods
+/// the importer expects. An image can export this alongside a vtable, so that an importer can
+/// catch a method that was renamed or changed signature before calling it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct VtableLayout {
+    /// The version of the trait the vtable was built for.
+    pub version: u32,
+    /// The number of methods in the vtable.
+    pub method_count: u32,
+    /// Points to `method_count` signature fingerprints, as in [`StableAbi::SIGNATURES`].
+    pub signatures: *const u64,
+}
+
+impl VtableLayout {
+    /// The layout of the vtables built for `Trait` in this image.
+    pub const fn of<Trait: ?Sized + StableAbi>() -> Self {
+        Self {
+            version: Trait::VERSION,
+            method_count: Trait::SIGNATURES.len() as u32,
+            signatures: Trait::SIGNATURES.as_ptr(),
+        }
+    }
+
+    /// Checks that a vtable with this layout can be imported as `Trait`.
+    ///
+    /// Either layout may have more methods than the other, but the methods they share must
+    /// have the same signatures.
+    ///
+    /// # Safety
+    /// `self.signatures` must point to `self.method_count` fingerprints.
+    pub unsafe fn check<Trait: ?Sized + StableAbi>(&self) -> Result<(), LayoutError> {
+        if self.version < Trait::MIN_VERSION {
+            return Err(LayoutError::TooOld {
+                version: self.version,
+            });
+        }
+        // SAFETY: The caller guarantees `signatures` is valid.
+        let signatures =
+            unsafe { core::slice::from_raw_parts(self.signatures, self.method_count as usize) };
+        match Trait::SIGNATURES
+            .iter()
+            .zip(signatures)
+            .position(|(ours, theirs)| ours != theirs)
+        {
+            Some(index) => Err(LayoutError::SignatureMismatch { index }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Why a [`VtableLayout`] can't be imported as a trait.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The vtable is older than [`StableAbi::MIN_VERSION`].
+    TooOld {
+        /// The version of the vtable.
+        version: u32,
+    },
+    /// The method at `index` has a different name or signature.
+    SignatureMismatch {
+        /// The index of the method in the vtable.
+        index: usize,
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooOld { version } => write!(f, "vtable version {version} is too old"),
+            Self::SignatureMismatch { index } => {
+                write!(f, "method {index} of the vtable has a different signature")
+            }
+        }
+    }
+}
+
+impl core::error::Error for LayoutError {}
+
+/// Reads the header of `vtable`, rejecting it if it's missing required methods.
+///
+/// # Safety
+/// `vtable` must point to a valid stable vtable of `Trait`.
+unsafe fn versioned_metadata<Trait>(vtable: NonNull<Trait::VTable>) -> Option<Trait::Metadata>
+where
+    Trait: ?Sized + StableAbi,
+{
+    // SAFETY: Every stable vtable starts with a header.
+    let header = unsafe { vtable.cast::<AbiHeader>().read() };
+    if header.version < Trait::MIN_VERSION || (header.size as usize) < Trait::MIN_SIZE {
+        return None;
+    }
+    // SAFETY: The vtable is large enough to have every required method.
+    Some(unsafe { Trait::metadata_from_ptr(vtable) })
+}
+
+impl<'a, Trait: ?Sized + StableAbi> Ref<'a, Trait> {
+    /// Wraps an object from another image, whose vtable may be older or newer than this one.
+    ///
+    /// Returns `None` if the vtable is older than [`StableAbi::MIN_VERSION`] or too small to
+    /// hold its methods. Methods added after `MIN_VERSION` may be missing from the vtable:
+    /// calling one of them runs its default body, while the `try_` methods of the generated
+    /// `{Trait}Versioned` trait return `None` instead.
+    ///
+    /// # Safety
+    ///
+    /// - `vtable` must point to a stable vtable of `Trait`, with a header that correctly
+    ///   describes it, valid for the `'static` lifetime.
+    /// - Calling each function in the vtable with `ctx` must uphold the contract of its method
+    ///   for the lifetime `'a`.
+    /// - If `Trait` has auto trait supertraits, like `Send`, the object must meet them.
+    pub unsafe fn try_from_versioned(
+        ctx: NonNull<c_void>,
+        vtable: NonNull<Trait::VTable>,
+    ) -> Option<Self> {
+        // SAFETY: The caller guarantees the validity of `vtable` and `ctx`.
+        unsafe {
+            let meta = versioned_metadata::<Trait>(vtable)?;
+            Some(Self::from_raw_parts(ctx.cast(), meta))
+        }
+    }
+}
+
+impl<'a, Trait: ?Sized + StableAbi> RefMut<'a, Trait> {
+    /// Wraps an object from another image, whose vtable may be older or newer than this one.
+    ///
+    /// See [`Ref::try_from_versioned`].
+    ///
+    /// # Safety
+    ///
+    /// - `vtable` must point to a stable vtable of `Trait`, with a header that correctly
+    ///   describes it, valid for the `'static` lifetime.
+    /// - Calling each function in the vtable with `ctx` must uphold the contract of its method
+    ///   for the lifetime `'a`. `ctx` must not be accessed by anything else during `'a`.
+    /// - If `Trait` has auto trait supertraits, like `Send`, the object must meet them.
+    pub unsafe fn try_from_versioned(
+        ctx: NonNull<c_void>,
+        vtable: NonNull<Trait::VTable>,
+    ) -> Option<Self> {
+        // SAFETY: The caller guarantees the validity of `vtable` and `ctx`.
+        unsafe {
+            let meta = versioned_metadata::<Trait>(vtable)?;
+            Some(Self::from_raw_parts(ctx.cast(), meta))
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Converting between tinydyn trait objects and std `&dyn Trait` trait objects.
+
+use core::ptr::NonNull;
+
+use crate::{DynTrait, PlainDyn, Ref, RefMut};
+
+/// A tinydyn trait object that converts to and from the std trait object of its trait.
+///
+/// Implemented by `#[tinydyn]`, unless the trait has the `repr_c`, `stable_abi`, `partial_eq`,
+/// `eq` or `hash` options, or `where Self: ..` methods. This enables:
+///
+/// - [`Ref::from_std_dyn`] and [`RefMut::from_std_dyn`], which wrap a `&dyn Trait` in a
+///   tinydyn object whose vtable calls through the std vtable.
+/// - [`Ref::as_std_dyn`] and [`RefMut::as_std_dyn`], which return the generated
+///   `{Trait}Std` adapter. It implements `Trait` by calling through the tinydyn object, and can
+///   be coerced to a `&dyn Trait`. If `Trait` has `&mut self` methods, only the adapter of a
+///   `RefMut` implements it.
+///
+/// ```ignore
+/// use tinydyn::{tinydyn, Ref};
+///
+/// #[tinydyn]
+/// trait Sensor {
+///     fn read(&self) -> i32;
+/// }
+///
+/// struct Fixed(i32);
+/// impl Sensor for Fixed {
+///     fn read(&self) -> i32 {
+///         self.0
+///     }
+/// }
+///
+/// fn legacy_read(sensor: &dyn Sensor) -> i32 {
+///     sensor.read()
+/// }
+///
+/// let std_sensor: &dyn Sensor = &Fixed(7);
+/// let sensor: Ref<dyn Sensor> = Ref::from_std_dyn(&std_sensor);
+/// assert_eq!(legacy_read(&sensor.as_std_dyn()), 7);
+/// ```
+///
+/// # Safety
+/// `STD_REF_METADATA` and `STD_MUT_METADATA` must be valid to call the trait methods with a
+/// pointer to a `&Std<'_>` and a `&mut Std<'_>`, respectively.
+pub unsafe trait StdDyn: PlainDyn {
+    /// The std trait object, `dyn Trait + 'b`.
+    type Std<'b>: ?Sized + 'b;
+
+    /// The generated `{Trait}Std<P>`, which implements `Trait` by calling through `P`.
+    type Adapter<P>;
+
+    /// The metadata for calling methods on a `&Std<'_>`.
+    ///
+    /// Calling its `&mut self` methods is undefined behavior, which is why it's only given to
+    /// a `Ref`.
+    #[doc(hidden)]
+    const STD_REF_METADATA: Self::Metadata;
+
+    /// The metadata for calling methods on a `&mut Std<'_>`.
+    #[doc(hidden)]
+    const STD_MUT_METADATA: Self::Metadata;
+
+    /// Wraps `ptr` in the adapter.
+    #[doc(hidden)]
+    fn adapter<P>(ptr: P) -> Self::Adapter<P>;
+}
+
+impl<'a, Trait: ?Sized + StdDyn> Ref<'a, Trait> {
+    /// Wraps a std trait object, calling its methods through the std vtable.
+    pub fn from_std_dyn<'b>(object: &'a &'b Trait::Std<'b>) -> Self {
+        // SAFETY: The metadata calls methods on a `&Std<'_>`, which `object` points to.
+        unsafe { Self::from_raw_parts(NonNull::from(object).cast(), Trait::STD_REF_METADATA) }
+    }
+}
+
+impl<'a, Trait: ?Sized + StdDyn> RefMut<'a, Trait> {
+    /// Wraps a mutable std trait object, calling its methods through the std vtable.
+    pub fn from_std_dyn<'b>(object: &'a mut &'b mut Trait::Std<'b>) -> Self {
+        // SAFETY: The metadata calls methods on a `&mut Std<'_>`, which `object` uniquely points
+        // to.
+        unsafe { Self::from_raw_parts(NonNull::from(object).cast(), Trait::STD_MUT_METADATA) }
+    }
+}
+
+impl<'a, Trait> Ref<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: StdDyn,
+{
+    /// Gets an adapter that implements `Trait`, for APIs that need a `&dyn Trait`.
+    ///
+    /// If `Trait` has `&mut self` methods, the adapter doesn't implement it, since they can't be
+    /// called through a `Ref`. Use [`RefMut::as_std_dyn`] instead.
+    pub fn as_std_dyn(&self) -> <Trait::Plain as StdDyn>::Adapter<Self> {
+        <Trait::Plain as StdDyn>::adapter(*self)
+    }
+}
+
+impl<'a, Trait> RefMut<'a, Trait>
+where
+    Trait: ?Sized + DynTrait,
+    Trait::Plain: StdDyn,
+{
+    /// Gets an adapter that implements `Trait`, for APIs that need a `&dyn Trait` or
+    /// `&mut dyn Trait`.
+    pub fn as_std_dyn(&mut self) -> <Trait::Plain as StdDyn>::Adapter<RefMut<'_, Trait>> {
+        <Trait::Plain as StdDyn>::adapter(self.as_mut())
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Vtables tied to the concrete type they call methods on.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+use crate::{BuildDynMeta, PlainDyn, Ref, RefMut};
+
+/// A vtable for `Trait` whose methods are called on a `T`.
+///
+/// Unlike a bare `{Trait}Vtable`, this can be safely paired with a `&T` or `&mut T`, using
+/// [`Ref::from_vtable`] and [`RefMut::from_vtable`].
+///
+/// Get one for the `Trait` impl of `T` with [`VTableFor::of_impl`], or assemble one from
+/// function pointers with the generated `{Trait}Vtable::builder`:
+///
+/// ```ignore
+/// #[tinydyn]
+/// trait Foo {
+///     fn blah(&self) -> i32;
+/// }
+///
+/// struct Context(i32);
+///
+/// static FAKE: VTableFor<dyn Foo, Context> = FooVtable::builder::<Context>()
+///     .blah(|ctx| ctx.0 * 2)
+///     .build()
+///     .unwrap();
+///
+/// let foo = Ref::from_vtable(&Context(4), &FAKE);
+/// assert_eq!(foo.blah(), 8);
+/// ```
+#[repr(transparent)]
+pub struct VTableFor<Trait: ?Sized + PlainDyn, T> {
+    vtable: Trait::VTable,
+    _concrete: PhantomData<fn(T)>,
+}
+
+impl<Trait: ?Sized + PlainDyn, T> VTableFor<Trait, T> {
+    /// Ties `vtable` to the concrete type `T`.
+    ///
+    /// # Safety
+    /// `vtable` must be valid to call the methods of `Trait` with a pointer to `T`.
+    pub const unsafe fn new_unchecked(vtable: Trait::VTable) -> Self {
+        Self {
+            vtable,
+            _concrete: PhantomData,
+        }
+    }
+
+    /// Gets the vtable of `Trait` built from the impl for `T`.
+    pub const fn of_impl() -> &'static Self
+    where
+        Trait::LocalNewtype<T>: BuildDynMeta<Trait>,
+    {
+        let vtable = <Trait::LocalNewtype<T> as BuildDynMeta<Trait>>::VTABLE_REF;
+        // SAFETY: `VTableFor` is `repr(transparent)` over the vtable, which was built for `T`.
+        unsafe { &*(vtable as *const Trait::VTable as *const Self) }
+    }
+
+    /// Gets the untyped vtable.
+    pub const fn get(&self) -> &Trait::VTable {
+        &self.vtable
+    }
+}
+
+impl<Trait: ?Sized + PlainDyn, T> Copy for VTableFor<Trait, T> {}
+impl<Trait: ?Sized + PlainDyn, T> Clone for VTableFor<Trait, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Trait: ?Sized + PlainDyn, T> fmt::Debug for VTableFor<Trait, T>
+where
+    Trait::VTable: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.vtable.fmt(f)
+    }
+}
+
+impl<'a, Trait: ?Sized + PlainDyn> Ref<'a, Trait> {
+    /// Pairs a reference with a vtable built for its type.
+    ///
+    /// To include `Send` or `Sync` in the trait object, use [`Ref::from_raw_parts`].
+    pub fn from_vtable<T>(data: &'a T, vtable: &'static VTableFor<Trait, T>) -> Self {
+        let meta = Trait::metadata_from_vtable(&vtable.vtable);
+        // SAFETY: `vtable` was built to call methods on a `T`.
+        unsafe { Self::from_raw_parts(NonNull::from(data).cast(), meta) }
+    }
+}
+
+impl<'a, Trait: ?Sized + PlainDyn> RefMut<'a, Trait> {
+    /// Pairs a mutable reference with a vtable built for its type.
+    ///
+    /// To include `Send` or `Sync` in the trait object, use [`RefMut::from_raw_parts`].
+    pub fn from_vtable<T>(data: &'a mut T, vtable: &'static VTableFor<Trait, T>) -> Self {
+        let meta = Trait::metadata_from_vtable(&vtable.vtable);
+        // SAFETY: `vtable` was built to call methods on a `T`.
+        unsafe { Self::from_raw_parts(NonNull::from(data).cast(), meta) }
+    }
+}
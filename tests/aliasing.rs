@@ -0,0 +1,211 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exercises every receiver combination through `Ref` and `RefMut`, for checking the pointer
+//! plumbing with `cargo +nightly miri test` under Stacked and Tree Borrows.
+
+use core::cell::Cell;
+
+use tinydyn::{tinydyn, Ref, RefMut};
+
+// Without `ref_impls`, so calls on `Ref` and `RefMut` go through `Deref`.
+#[tinydyn(downcast)]
+trait Slot {
+    fn get(&self) -> u32;
+    fn set(&mut self, value: u32);
+    fn name(&self) -> &str;
+    fn slot(&mut self) -> &mut u32;
+}
+
+// `&self` only, so `Ref` implements it too and calls the vtable directly.
+#[tinydyn(ref_impls)]
+trait Tick {
+    fn tick(&self) -> u32;
+}
+
+#[tinydyn(ref_impls)]
+trait Grow {
+    fn grow(&mut self, by: u32) -> u32;
+}
+
+tinydyn::combine!(trait SlotGrow = Slot + Grow);
+
+#[derive(Default)]
+struct Cellar {
+    // Not the first field, so the data pointer is checked to keep the provenance of the whole
+    // `Cellar`.
+    name: String,
+    value: u32,
+    ticks: Cell<u32>,
+}
+
+impl Slot for Cellar {
+    fn get(&self) -> u32 {
+        self.value
+    }
+
+    fn set(&mut self, value: u32) {
+        self.value = value;
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn slot(&mut self) -> &mut u32 {
+        &mut self.value
+    }
+}
+
+impl Tick for Cellar {
+    fn tick(&self) -> u32 {
+        self.ticks.set(self.ticks.get() + 1);
+        self.ticks.get()
+    }
+}
+
+impl Grow for Cellar {
+    fn grow(&mut self, by: u32) -> u32 {
+        self.value += by;
+        self.name.push('+');
+        self.value
+    }
+}
+
+fn cellar() -> Cellar {
+    Cellar {
+        name: "cellar".into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn shared_receivers() {
+    let x = cellar();
+    let a: Ref<dyn Slot> = Ref::new(&x);
+    let b = a;
+    // `Slot` has `&mut self` methods, so a `Ref` calls these through `Deref` to `DynTarget`.
+    assert_eq!(a.get(), 0);
+    assert_eq!(Slot::get(&*b), 0);
+    let name = a.name();
+    assert_eq!(b.name(), name);
+    assert_eq!(x.name, "cellar");
+    assert_eq!(a.downcast_ref::<Cellar>().unwrap().value, 0);
+}
+
+#[test]
+fn interior_mutability_through_shared() {
+    let x = cellar();
+    let a: Ref<dyn Tick> = Ref::new(&x);
+    let b = a;
+    // Through the impl on `Ref`, and through `Deref` to `DynTarget`.
+    assert_eq!(a.tick(), 1);
+    assert_eq!(Tick::tick(&*b), 2);
+    assert_eq!(Tick::tick(&b), 3);
+    assert_eq!(x.ticks.get(), 3);
+}
+
+#[test]
+fn mutable_receivers() {
+    let mut x = cellar();
+    let mut r: RefMut<dyn Slot> = RefMut::new(&mut x);
+    r.set(1);
+    assert_eq!(r.get(), 1);
+    Slot::set(&mut *r, 2);
+    assert_eq!(Slot::get(&*r), 2);
+
+    // A `&mut` borrowed out of the concrete value can be written through.
+    let slot = r.slot();
+    *slot += 1;
+    assert_eq!(r.get(), 3);
+    r.downcast_mut::<Cellar>().unwrap().value = 4;
+    assert_eq!(r.downcast_ref::<Cellar>().unwrap().value, 4);
+    assert_eq!(x.value, 4);
+}
+
+#[test]
+fn reborrows() {
+    let mut x = cellar();
+    let mut r: RefMut<dyn Slot> = RefMut::new(&mut x);
+    {
+        let mut inner = r.as_mut();
+        inner.set(1);
+        let mut innermost = inner.as_mut();
+        *innermost.slot() += 1;
+        assert_eq!(inner.get(), 2);
+    }
+    let shared = r.as_ref();
+    let copy = shared;
+    assert_eq!(shared.get() + copy.get(), 4);
+    r.set(5);
+    let shared: Ref<dyn Slot> = r.into();
+    assert_eq!(shared.get(), 5);
+    assert_eq!(x.value, 5);
+}
+
+#[test]
+fn raw_parts_round_trip() {
+    let mut x = cellar();
+    let r: RefMut<dyn Slot> = RefMut::new(&mut x);
+    let (data, meta) = r.into_raw_parts();
+    // SAFETY: These came from a `RefMut` that was consumed.
+    let mut r = unsafe { RefMut::<dyn Slot>::from_raw_parts(data, meta) };
+    r.set(6);
+    let (data, meta) = r.into_raw_parts();
+    // SAFETY: The `RefMut` was consumed, and only shared refs are made from here on.
+    let shared = unsafe { Ref::<dyn Slot>::from_raw_parts(data, meta) };
+    assert_eq!(shared.get(), 6);
+    assert_eq!(x.value, 6);
+}
+
+#[test]
+fn nested_handles() {
+    let mut x = cellar();
+    let mut inner: RefMut<dyn Grow> = RefMut::new(&mut x);
+    let mut outer: RefMut<dyn Grow> = RefMut::new(&mut inner);
+    assert_eq!(outer.grow(1), 1);
+    assert_eq!(outer.as_mut().grow(1), 2);
+    assert_eq!(inner.grow(1), 3);
+
+    let y = cellar();
+    let inner: Ref<dyn Tick> = Ref::new(&y);
+    let outer: Ref<dyn Tick> = Ref::new(&inner);
+    assert_eq!(outer.tick() + inner.tick(), 3);
+    assert_eq!(x.name, "cellar+++");
+}
+
+#[test]
+fn combined_receivers() {
+    let mut x = cellar();
+    let mut both: RefMut<dyn SlotGrow> = RefMut::new(&mut x);
+    assert_eq!(both.grow(2), 2);
+    let value = both.get();
+    both.set(value + 1);
+    *both.slot() += 1;
+    assert_eq!(both.as_ref().get(), 4);
+    assert_eq!(both.name(), "cellar+");
+    assert_eq!(x.value, 4);
+}
+
+#[test]
+fn std_trait_objects() {
+    let mut x = cellar();
+    let mut std_mut: &mut dyn Slot = &mut x;
+    let mut r: RefMut<dyn Slot> = RefMut::from_std_dyn(&mut std_mut);
+    r.set(7);
+    *r.slot() += 1;
+    let adapter = r.as_std_dyn();
+    assert_eq!(adapter.get(), 8);
+    assert_eq!(x.value, 8);
+}
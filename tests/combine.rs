@@ -0,0 +1,79 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tinydyn::{tinydyn, Ref, RefMut};
+
+#[tinydyn]
+trait Sensor {
+    fn read(&self) -> i32;
+}
+
+#[tinydyn(downcast)]
+trait Configurable {
+    fn offset(&self) -> i32;
+    fn set_offset(&mut self, offset: i32);
+}
+
+tinydyn::combine!(
+    /// A sensor that can be calibrated.
+    pub trait SensorAndConfig = Sensor + Configurable;
+);
+
+struct Thermometer {
+    raw: i32,
+    offset: i32,
+}
+
+impl Sensor for Thermometer {
+    fn read(&self) -> i32 {
+        self.raw + self.offset
+    }
+}
+
+impl Configurable for Thermometer {
+    fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    fn set_offset(&mut self, offset: i32) {
+        self.offset = offset;
+    }
+}
+
+fn calibrate(mut device: RefMut<dyn SensorAndConfig>, expected: i32) {
+    let error = device.read() - expected;
+    let offset = device.offset();
+    device.set_offset(offset - error);
+}
+
+#[test]
+fn call_both_traits() {
+    let mut thermometer = Thermometer { raw: 25, offset: 3 };
+    calibrate(RefMut::new(&mut thermometer), 20);
+    assert_eq!(thermometer.offset, -5);
+
+    let device: Ref<dyn SensorAndConfig + Send + Sync> = Ref::new(&thermometer);
+    assert_eq!(device.read(), 20);
+}
+
+#[test]
+fn combined_trait_object_implements_combination() {
+    fn reading_and_offset(device: &(impl SensorAndConfig + ?Sized)) -> (i32, i32) {
+        (device.read(), device.offset())
+    }
+
+    let thermometer = Thermometer { raw: 7, offset: 1 };
+    let device: Ref<dyn SensorAndConfig> = Ref::new(&thermometer);
+    assert_eq!(reading_and_offset(&*device), (8, 1));
+}
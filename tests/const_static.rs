@@ -0,0 +1,79 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tinydyn::{tinydyn, Ref};
+
+#[tinydyn]
+trait Driver {
+    fn id(&self) -> u32;
+    fn name(&self) -> &'static str {
+        "unnamed"
+    }
+}
+
+#[tinydyn]
+trait Irq {
+    fn handle(&self) -> u32;
+}
+
+struct Uart(u32);
+struct Spi;
+
+impl Driver for Uart {
+    fn id(&self) -> u32 {
+        self.0
+    }
+
+    fn name(&self) -> &'static str {
+        "uart"
+    }
+}
+
+impl Driver for Spi {
+    fn id(&self) -> u32 {
+        100
+    }
+}
+
+impl Irq for Uart {
+    fn handle(&self) -> u32 {
+        self.0 * 2
+    }
+}
+
+static DRIVERS: [Ref<'static, dyn Driver + Sync>; 3] =
+    [Ref::new(&Uart(0)), Ref::new(&Spi), Ref::new(&Uart(1))];
+
+static IRQS: [Ref<'static, dyn Irq + Send + Sync>; 2] = [Ref::new(&Uart(2)), Ref::new(&Uart(3))];
+
+const SPI: Ref<'static, dyn Driver> = Ref::new(&Spi);
+
+#[test]
+fn static_table() {
+    let ids: Vec<u32> = DRIVERS.iter().map(|d| d.id()).collect();
+    assert_eq!(ids, [0, 100, 1]);
+    let names: Vec<&str> = DRIVERS.iter().map(|d| d.name()).collect();
+    assert_eq!(names, ["uart", "unnamed", "uart"]);
+}
+
+#[test]
+fn static_inline_vtable() {
+    let handled: Vec<u32> = IRQS.iter().map(|irq| irq.handle()).collect();
+    assert_eq!(handled, [4, 6]);
+}
+
+#[test]
+fn const_item() {
+    assert_eq!(SPI.id(), 100);
+}
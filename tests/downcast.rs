@@ -0,0 +1,66 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tinydyn::{tinydyn, Ref, RefMut};
+
+#[tinydyn(downcast)]
+trait Device {
+    fn poll(&mut self) -> u32;
+}
+
+struct Uart(u32);
+
+impl Device for Uart {
+    fn poll(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+struct Spi;
+
+impl Device for Spi {
+    fn poll(&mut self) -> u32 {
+        0
+    }
+}
+
+#[test]
+fn downcast_ref() {
+    let (uart, spi) = (Uart(5), Spi);
+    let x: Ref<dyn Device> = Ref::new(&uart);
+    assert!(x.is::<Uart>());
+    assert!(!x.is::<Spi>());
+    assert_eq!(x.downcast_ref::<Uart>().map(|u| u.0), Some(5));
+    assert!(x.downcast_ref::<u32>().is_none());
+
+    let y: Ref<dyn Device + Send + Sync> = Ref::new(&spi);
+    assert!(y.downcast_ref::<Spi>().is_some());
+    assert!(y.downcast_ref::<Uart>().is_none());
+}
+
+#[test]
+fn downcast_mut() {
+    let mut uart = Uart(0);
+    let mut x: RefMut<dyn Device> = RefMut::new(&mut uart);
+    assert_eq!(x.poll(), 1);
+    assert!(x.downcast_mut::<Spi>().is_none());
+    x.downcast_mut::<Uart>().unwrap().0 = 10;
+    assert_eq!(x.poll(), 11);
+    let Err(x) = x.into_downcast::<Spi>() else {
+        panic!("downcast to the wrong type");
+    };
+    let uart = x.into_downcast::<Uart>().ok().unwrap();
+    assert_eq!(uart.0, 11);
+}
@@ -0,0 +1,97 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+use tinydyn::{tinydyn, Ref};
+
+#[tinydyn(eq, hash)]
+trait Key {
+    fn id(&self) -> u32;
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct Small(u8);
+
+impl Key for Small {
+    fn id(&self) -> u32 {
+        self.0.into()
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct Large(u32);
+
+impl Key for Large {
+    fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+#[tinydyn(partial_eq)]
+trait Measure {
+    fn value(&self) -> f32;
+}
+
+impl Measure for f32 {
+    fn value(&self) -> f32 {
+        *self
+    }
+}
+
+#[test]
+fn eq_compares_values() {
+    let (a, b, c) = (Small(1), Small(1), Small(2));
+    let x: Ref<dyn Key> = Ref::new(&a);
+    assert!(x == Ref::new(&b));
+    assert!(x != Ref::new(&c));
+}
+
+#[test]
+fn eq_different_types() {
+    let (a, b) = (Small(1), Large(1));
+    let x: Ref<dyn Key> = Ref::new(&a);
+    let y: Ref<dyn Key> = Ref::new(&b);
+    assert_eq!(x.id(), y.id());
+    assert!(x != y);
+}
+
+#[test]
+fn eq_with_markers() {
+    let (a, b) = (Small(3), Small(3));
+    let x: Ref<dyn Key + Send + Sync> = Ref::new(&a);
+    assert!(x == Ref::new(&b));
+    assert!(x.remove_marker::<dyn Send>() == Ref::new(&b));
+}
+
+#[test]
+fn partial_eq() {
+    let (a, b) = (1.0f32, f32::NAN);
+    let x: Ref<dyn Measure> = Ref::new(&a);
+    let y: Ref<dyn Measure> = Ref::new(&b);
+    assert!(x == x);
+    assert!(y != y);
+}
+
+#[test]
+fn hash_set_dedup() {
+    let keys = [Small(1), Small(1), Small(2)];
+    let large = Large(1);
+    let mut set: HashSet<Ref<dyn Key>> = keys.iter().map(Ref::new).collect();
+    assert_eq!(set.len(), 2);
+    assert!(set.insert(Ref::new(&large)));
+    assert!(!set.insert(Ref::new(&Small(2))));
+    assert!(set.contains(&Ref::new(&Large(1))));
+    assert!(!set.contains(&Ref::new(&Large(2))));
+}
@@ -0,0 +1,97 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt::{self, Debug, Display};
+
+use tinydyn::{combine, tinydyn, ByAddress, Ref, RefMut};
+
+#[tinydyn]
+trait Sensor: Debug {
+    fn read(&self) -> i32;
+}
+
+#[tinydyn]
+trait Device: fmt::Display + Send {
+    fn reset(&mut self);
+}
+
+#[tinydyn]
+trait Plain {
+    fn get(&self) -> i32;
+}
+
+#[derive(Debug)]
+struct Thermometer {
+    celsius: i32,
+}
+
+impl Sensor for Thermometer {
+    fn read(&self) -> i32 {
+        self.celsius
+    }
+}
+
+impl Display for Thermometer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}°C", self.celsius)
+    }
+}
+
+impl Device for Thermometer {
+    fn reset(&mut self) {
+        self.celsius = 0;
+    }
+}
+
+impl Plain for Thermometer {
+    fn get(&self) -> i32 {
+        self.celsius
+    }
+}
+
+combine!(trait SensorDevice = Plain + Sensor + Device);
+
+#[test]
+fn debug_supertrait() {
+    let x = Thermometer { celsius: 21 };
+    let sensor: Ref<dyn Sensor> = Ref::new(&x);
+    assert_eq!(format!("{sensor:?}"), "Thermometer { celsius: 21 }");
+    assert_eq!(format!("{:?}", &*sensor), "Thermometer { celsius: 21 }");
+    assert_eq!(format!("{sensor:#?}"), format!("{x:#?}"));
+    assert!(format!("{:?}", ByAddress(sensor)).starts_with("ByAddress { data: 0x"));
+}
+
+#[test]
+fn display_supertrait() {
+    let mut x = Thermometer { celsius: 21 };
+    let mut device: RefMut<dyn Device + Send> = RefMut::new(&mut x);
+    assert_eq!(device.to_string(), "21°C");
+    device.reset();
+    assert_eq!(format!("{device:>5}"), "0°C");
+    assert!(format!("{device:?}").starts_with("RefMut { data: 0x"));
+}
+
+#[test]
+fn combined() {
+    let x = Thermometer { celsius: 5 };
+    let both: Ref<dyn SensorDevice> = Ref::new(&x);
+    assert_eq!(both.read(), 5);
+    assert_eq!(
+        format!("{both:?} is {both}"),
+        "Thermometer { celsius: 5 } is 5°C"
+    );
+
+    let plain: Ref<dyn Plain> = Ref::new(&x);
+    assert!(format!("{plain:?}").starts_with("Ref { data: 0x"));
+}
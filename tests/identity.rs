@@ -0,0 +1,113 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeSet, HashSet};
+
+use tinydyn::{tinydyn, ByAddress, Ref, RefMut};
+
+#[tinydyn]
+trait Subscriber {
+    fn notify(&self, event: u32);
+}
+
+impl Subscriber for u32 {
+    fn notify(&self, _event: u32) {}
+}
+
+#[tinydyn]
+trait Publisher {
+    fn publish(&self) -> u32;
+    fn topic(&self) -> &str;
+}
+
+impl Publisher for u32 {
+    fn publish(&self) -> u32 {
+        *self
+    }
+
+    fn topic(&self) -> &str {
+        "u32"
+    }
+}
+
+#[test]
+fn ptr_eq() {
+    let (a, b) = (1u32, 1u32);
+    let x: Ref<dyn Subscriber> = Ref::new(&a);
+    assert!(Ref::ptr_eq(&x, &Ref::new(&a)));
+    assert!(!Ref::ptr_eq(&x, &Ref::new(&b)));
+    assert_eq!(Ref::addr(&x), &a as *const u32 as usize);
+}
+
+#[test]
+fn by_address_dedup() {
+    let (a, b) = (1u32, 1u32);
+    let mut subscribers: HashSet<ByAddress<dyn Subscriber>> = HashSet::new();
+    assert!(subscribers.insert(ByAddress(Ref::new(&a))));
+    assert!(!subscribers.insert(ByAddress(Ref::new(&a))));
+    assert!(subscribers.insert(ByAddress(Ref::new(&b))));
+    assert!(subscribers.remove(&ByAddress(Ref::new(&a))));
+    assert_eq!(subscribers.len(), 1);
+    assert!(subscribers.contains(&ByAddress(Ref::new(&b))));
+
+    let ordered: BTreeSet<_> = [&a, &b, &a]
+        .into_iter()
+        .map(|x| ByAddress::from(Ref::<dyn Subscriber>::new(x)))
+        .collect();
+    assert_eq!(ordered.len(), 2);
+}
+
+#[test]
+fn by_address_same_data_different_type() {
+    struct Wrapper(u32);
+    impl Subscriber for Wrapper {
+        fn notify(&self, _event: u32) {}
+    }
+    let w = Wrapper(1);
+    let x: Ref<dyn Subscriber> = Ref::new(&w);
+    let y: Ref<dyn Subscriber> = Ref::new(&w.0);
+    assert_eq!(Ref::addr(&x), Ref::addr(&y));
+    assert!(ByAddress(x) != ByAddress(y));
+}
+
+#[test]
+fn debug() {
+    let mut a = 10u32;
+    let addr = format!("{:?}", &a as *const u32);
+    let x: Ref<dyn Subscriber> = Ref::new(&a);
+    let debug = format!("{x:?}");
+    assert!(debug.starts_with(&format!("Ref {{ data: {addr}, vtable: 0x")));
+    // An inline vtable has no room for the type name.
+    assert!(!debug.contains("type_name"));
+
+    let y: RefMut<dyn Subscriber> = RefMut::new(&mut a);
+    assert!(format!("{y:?}").starts_with(&format!("RefMut {{ data: {addr}, ")));
+}
+
+#[test]
+fn debug_type_name() {
+    let a = 10u32;
+    let x: Ref<dyn Publisher> = Ref::new(&a);
+    let debug = format!("{x:?}");
+    #[cfg(feature = "type_name")]
+    assert!(debug.ends_with(", type_name: \"u32\" }"));
+    #[cfg(not(feature = "type_name"))]
+    assert!(!debug.contains("type_name"));
+}
+
+#[test]
+fn type_name_keeps_inline_vtable() {
+    assert_eq!(size_of::<Ref<dyn Subscriber>>(), 2 * size_of::<usize>());
+    assert_eq!(size_of::<Ref<dyn Publisher>>(), 2 * size_of::<usize>());
+}
@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// The borrows through `self` are part of what's being tested.
+#![allow(clippy::needless_borrow)]
+
 use tinydyn::{tinydyn, Ref};
 
 #[derive(Clone, Copy)]
@@ -0,0 +1,128 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+
+use tinydyn::{combine, tinydyn, Marked, Ref, RefMut};
+
+trait IsrSafe {}
+
+#[tinydyn(markers(IsrSafe))]
+trait Worker {
+    fn id(&self) -> u32;
+
+    /// Runs on another thread, so it needs `&Self` to be shared.
+    fn spawn(&self) -> u32
+    where
+        Self: Sync;
+
+    fn take(&mut self) -> u32
+    where
+        Self: Send + Sync;
+}
+
+#[tinydyn]
+trait Named {
+    fn name(&self) -> &'static str;
+}
+
+combine!(trait NamedWorker = Worker + Named);
+
+struct Counter<T>(T);
+
+impl<T: Get> Worker for Counter<T> {
+    fn id(&self) -> u32 {
+        self.0.get()
+    }
+
+    fn spawn(&self) -> u32
+    where
+        Self: Sync,
+    {
+        thread::scope(|s| s.spawn(|| self.id() * 10).join().unwrap())
+    }
+
+    fn take(&mut self) -> u32
+    where
+        Self: Send + Sync,
+    {
+        self.id() + 1
+    }
+}
+
+impl<T> Named for Counter<T> {
+    fn name(&self) -> &'static str {
+        "counter"
+    }
+}
+
+impl<T> IsrSafe for Counter<T> {}
+
+trait Get {
+    fn get(&self) -> u32;
+}
+
+impl Get for AtomicU32 {
+    fn get(&self) -> u32 {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+impl Get for Cell<u32> {
+    fn get(&self) -> u32 {
+        Cell::get(self)
+    }
+}
+
+#[test]
+fn marked_methods() {
+    let mut x = Counter(AtomicU32::new(4));
+    let r: Ref<dyn Worker + Sync> = Ref::new(&x);
+    assert_eq!(r.id(), 4);
+    assert_eq!(r.spawn(), 40);
+    assert_eq!(r.remove_marker::<dyn Sync>().id(), 4);
+
+    let mut r: RefMut<dyn Worker + Send + Sync> = RefMut::new(&mut x);
+    assert_eq!(r.spawn(), 40);
+    assert_eq!(r.take(), 5);
+}
+
+#[test]
+fn unmarked_concrete_type() {
+    let x = Counter(Cell::new(2));
+    let r: Ref<dyn Worker + Send> = Ref::new(&x);
+    assert_eq!(r.id(), 2);
+}
+
+#[test]
+fn marked_raw_parts() {
+    let x = Counter(AtomicU32::new(4));
+    // The metadata must come from a trait object with the same markers.
+    let (data, meta) = Ref::<dyn Worker + Sync>::new(&x).to_raw_parts();
+    let r: Ref<dyn Worker + Sync> = unsafe { Ref::from_raw_parts(data, meta) };
+    assert_eq!(r.spawn(), 40);
+}
+
+#[test]
+fn user_marker_and_combined() {
+    let x = Counter(AtomicU32::new(3));
+    let r: Ref<Marked<dyn Worker + Sync, dyn IsrSafe>> = Ref::new(&x);
+    assert_eq!(r.spawn(), 30);
+
+    let r: Ref<dyn NamedWorker + Sync> = Ref::new(&x);
+    assert_eq!(r.name(), "counter");
+    assert_eq!(r.spawn(), 30);
+}
@@ -0,0 +1,107 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::marker::PhantomPinned;
+use std::cell::Cell;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+
+use tinydyn::{tinydyn, Marked, Ref, RefMut};
+
+/// Implemented by types that are safe to use from an interrupt handler.
+trait IsrSafe {}
+
+#[tinydyn(markers(Unpin, UnwindSafe, RefUnwindSafe, IsrSafe))]
+trait Counter {
+    fn get(&self) -> u32;
+    fn bump(&mut self);
+}
+
+impl IsrSafe for u32 {}
+
+impl Counter for u32 {
+    fn get(&self) -> u32 {
+        *self
+    }
+
+    fn bump(&mut self) {
+        *self += 1;
+    }
+}
+
+struct Pinned(u32, PhantomPinned);
+
+impl Counter for Pinned {
+    fn get(&self) -> u32 {
+        self.0
+    }
+
+    fn bump(&mut self) {
+        self.0 += 1;
+    }
+}
+
+impl Counter for Cell<u32> {
+    fn get(&self) -> u32 {
+        Cell::get(self)
+    }
+
+    fn bump(&mut self) {
+        *self.get_mut() += 1;
+    }
+}
+
+fn assert_unwind_safe<T: UnwindSafe>(_: &T) {}
+
+#[test]
+fn auto_markers() {
+    let x = 5u32;
+    let r: Ref<dyn Counter + Send + Sync + Unpin + UnwindSafe> = Ref::new(&x);
+    assert_eq!(r.get(), 5);
+    let r: Ref<dyn Counter + Unpin + UnwindSafe> =
+        r.remove_marker::<dyn Send>().remove_marker::<dyn Sync>();
+    assert_eq!(r.get(), 5);
+
+    let pinned = Pinned(3, PhantomPinned);
+    let r: Ref<dyn Counter + Send + Sync> = Ref::new(&pinned);
+    assert_eq!(r.get(), 3);
+
+    let mut cell = Cell::new(1);
+    let mut r: RefMut<dyn Counter + Send + UnwindSafe> = RefMut::new(&mut cell);
+    r.bump();
+    assert_eq!(r.get(), 2);
+}
+
+#[test]
+fn user_marker() {
+    fn interrupt(counter: RefMut<Marked<dyn Counter + Send, dyn IsrSafe>>) {
+        let mut counter = counter.remove_marker::<dyn IsrSafe>();
+        counter.bump();
+    }
+
+    let mut x = 5u32;
+    interrupt(RefMut::new(&mut x));
+    assert_eq!(x, 6);
+
+    let r: Ref<Marked<dyn Counter + Sync + Unpin, dyn IsrSafe>> = Ref::new(&x);
+    assert_eq!(r.get(), 6);
+}
+
+#[test]
+fn remove_all_markers() {
+    let x = 5u32;
+    let r: Ref<Marked<dyn Counter + Send + RefUnwindSafe, dyn IsrSafe>> = Ref::new(&x);
+    assert_unwind_safe(&r.remove_marker::<dyn IsrSafe>());
+    let plain: Ref<dyn Counter> = r.remove_all_markers();
+    assert_eq!(plain.get(), 5);
+}
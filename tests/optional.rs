@@ -0,0 +1,133 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tinydyn::{tinydyn, RefMut, VTableFor};
+
+#[tinydyn]
+trait Uart {
+    fn write(&mut self, byte: u8);
+
+    fn written(&self) -> &[u8];
+
+    #[tinydyn(optional)]
+    fn set_baud(&mut self, baud: u32) -> bool {
+        let _ = baud;
+        false
+    }
+
+    /// The default body can call other methods through the trait object.
+    #[tinydyn(optional)]
+    fn last(&self) -> Option<&u8> {
+        self.written().last()
+    }
+}
+
+#[derive(Default)]
+struct Pl011 {
+    out: Vec<u8>,
+    baud: u32,
+}
+
+#[tinydyn::overrides]
+impl Uart for Pl011 {
+    fn write(&mut self, byte: u8) {
+        self.out.push(byte);
+    }
+
+    fn written(&self) -> &[u8] {
+        &self.out
+    }
+
+    fn set_baud(&mut self, baud: u32) -> bool {
+        self.baud = baud;
+        true
+    }
+}
+
+#[derive(Default)]
+struct Loopback {
+    out: Vec<u8>,
+}
+
+#[tinydyn::overrides]
+impl Uart for Loopback {
+    fn write(&mut self, byte: u8) {
+        self.out.push(byte);
+    }
+
+    fn written(&self) -> &[u8] {
+        &self.out
+    }
+
+    fn last(&self) -> Option<&u8> {
+        None
+    }
+}
+
+#[test]
+fn has_method() {
+    let mut pl011 = Pl011::default();
+    let mut loopback = Loopback::default();
+    let pl011: RefMut<dyn Uart> = RefMut::new(&mut pl011);
+    let loopback: RefMut<dyn Uart> = RefMut::new(&mut loopback);
+    assert!(pl011.has_set_baud());
+    assert!(!pl011.has_last());
+    assert!(!loopback.has_set_baud());
+    assert!(loopback.has_last());
+}
+
+#[test]
+fn call_override() {
+    let mut pl011 = Pl011::default();
+    let mut uart: RefMut<dyn Uart> = RefMut::new(&mut pl011);
+    assert!(uart.set_baud(115200));
+    uart.write(7);
+    assert_eq!(uart.last(), Some(&7));
+    assert_eq!(pl011.baud, 115200);
+}
+
+#[test]
+fn call_default() {
+    let mut loopback = Loopback::default();
+    let mut uart: RefMut<dyn Uart> = RefMut::new(&mut loopback);
+    assert!(!uart.set_baud(9600));
+    uart.write(1);
+    assert_eq!(uart.last(), None);
+
+    let mut pl011 = Pl011::default();
+    let mut uart: RefMut<dyn Uart> = RefMut::new(&mut pl011);
+    uart.write(3);
+    // Runs the default body, which calls `written` through the vtable.
+    assert_eq!(uart.last(), Some(&3));
+}
+
+#[test]
+fn builder_leaves_optional_unset() {
+    fn write(this: &mut Pl011, byte: u8) {
+        this.out.push(byte);
+    }
+    fn written(this: &Pl011) -> &[u8] {
+        &this.out
+    }
+
+    static VTABLE: VTableFor<dyn Uart, Pl011> = UartVtable::builder()
+        .write(write)
+        .written(written)
+        .build()
+        .unwrap();
+    let mut pl011 = Pl011::default();
+    let mut uart = RefMut::from_vtable(&mut pl011, &VTABLE);
+    assert!(!uart.set_baud(9600));
+    assert_eq!(pl011.baud, 0);
+}
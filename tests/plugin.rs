@@ -0,0 +1,138 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Loads `tests/plugin`, which is built as a dev-dependency. Miri can't load shared libraries.
+#![cfg(all(target_os = "linux", not(miri)))]
+
+use tinydyn::plugin::{Plugin, PluginError};
+use tinydyn::{tinydyn, LayoutError, Ref};
+
+// The host's copies of the plugin's `Shape`, at different versions.
+mod same {
+    use super::*;
+
+    #[tinydyn(stable_abi(version = 2, min_version = 1))]
+    pub trait Shape {
+        #[tinydyn(since = 1)]
+        fn sides(&self) -> u32;
+
+        #[tinydyn(since = 2)]
+        fn scaled_area(&self, _scale: f32) -> f32 {
+            0.0
+        }
+    }
+}
+
+mod older {
+    use super::*;
+
+    #[tinydyn(stable_abi(version = 1))]
+    pub trait Shape {
+        #[tinydyn(since = 1)]
+        fn sides(&self) -> u32;
+    }
+}
+
+mod newer {
+    use super::*;
+
+    #[tinydyn(stable_abi(version = 3, min_version = 1))]
+    pub trait Shape {
+        #[tinydyn(since = 1)]
+        fn sides(&self) -> u32;
+
+        #[tinydyn(since = 2)]
+        fn scaled_area(&self, _scale: f32) -> f32 {
+            0.0
+        }
+
+        #[tinydyn(since = 3)]
+        fn is_regular(&self) -> bool {
+            false
+        }
+    }
+}
+
+mod changed {
+    use super::*;
+
+    #[tinydyn(stable_abi(version = 2, min_version = 1))]
+    pub trait Shape {
+        #[tinydyn(since = 1)]
+        fn sides(&self) -> u32;
+
+        #[tinydyn(since = 2)]
+        fn scaled_area(&self, _scale: f64) -> f64 {
+            0.0
+        }
+    }
+}
+
+fn open_plugin() -> Plugin {
+    let path = std::env::current_exe()
+        .unwrap()
+        .with_file_name("libtinydyn_test_plugin.so");
+    unsafe { Plugin::open(path) }.unwrap()
+}
+
+#[test]
+fn load_same_version() {
+    use same::{Shape, ShapeVersioned};
+
+    let plugin = open_plugin();
+    let shape: Ref<dyn Shape> = unsafe { plugin.get("tinydyn_test_square") }.unwrap();
+    assert_eq!(shape.sides(), 4);
+    assert_eq!(shape.scaled_area(0.5), 4.5);
+    assert_eq!(shape.try_scaled_area(2.0), Some(18.0));
+}
+
+#[test]
+fn load_into_older_host() {
+    use older::Shape;
+
+    let plugin = open_plugin();
+    let shape: Ref<dyn Shape> = unsafe { plugin.get("tinydyn_test_square") }.unwrap();
+    assert_eq!(shape.sides(), 4);
+}
+
+#[test]
+fn load_into_newer_host() {
+    use newer::{Shape, ShapeVersioned};
+
+    let plugin = open_plugin();
+    let shape: Ref<dyn Shape> = unsafe { plugin.get("tinydyn_test_square") }.unwrap();
+    assert_eq!(shape.scaled_area(1.0), 9.0);
+    assert_eq!(shape.try_is_regular(), None);
+    // The plugin's vtable is older, so this runs the default body.
+    assert!(!shape.is_regular());
+}
+
+#[test]
+fn changed_signature() {
+    let plugin = open_plugin();
+    let err = unsafe { plugin.get::<dyn changed::Shape>("tinydyn_test_square") }.unwrap_err();
+    assert!(matches!(
+        err,
+        PluginError::Layout(LayoutError::SignatureMismatch { index: 1 })
+    ));
+}
+
+#[test]
+fn bad_descriptor() {
+    let plugin = open_plugin();
+    let err = unsafe { plugin.get::<dyn same::Shape>("tinydyn_test_bad_version") }.unwrap_err();
+    assert!(matches!(err, PluginError::InvalidDescriptor));
+    let err = unsafe { plugin.get::<dyn same::Shape>("tinydyn_test_missing") }.unwrap_err();
+    assert!(matches!(err, PluginError::Load(_)));
+}
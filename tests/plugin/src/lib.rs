@@ -0,0 +1,58 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A plugin exporting `dyn Shape` objects, loaded by `tests/plugin.rs`.
+//!
+//! The host declares its own copies of `Shape`, as if it were built separately.
+
+use tinydyn::plugin::PluginDescriptor;
+use tinydyn::{tinydyn, Ref};
+
+#[tinydyn(stable_abi(version = 2, min_version = 1))]
+pub trait Shape {
+    #[tinydyn(since = 1)]
+    fn sides(&self) -> u32;
+
+    #[tinydyn(since = 2)]
+    fn scaled_area(&self, _scale: f32) -> f32 {
+        0.0
+    }
+}
+
+struct Square(f32);
+
+impl Shape for Square {
+    fn sides(&self) -> u32 {
+        4
+    }
+
+    fn scaled_area(&self, scale: f32) -> f32 {
+        self.0 * self.0 * scale
+    }
+}
+
+static SQUARE: Square = Square(3.0);
+
+#[no_mangle]
+pub extern "C" fn tinydyn_test_square() -> PluginDescriptor {
+    PluginDescriptor::new::<dyn Shape>(Ref::new(&SQUARE))
+}
+
+#[no_mangle]
+pub extern "C" fn tinydyn_test_bad_version() -> PluginDescriptor {
+    PluginDescriptor {
+        descriptor_version: 0,
+        ..tinydyn_test_square()
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::cell::Cell;
+
+use tinydyn::{tinydyn, Ref, RefMut};
+
+#[tinydyn(query)]
+trait Device {
+    fn name(&self) -> &str;
+}
+
+#[tinydyn]
+trait Dimmable {
+    fn set_level(&mut self, level: u8);
+}
+
+#[tinydyn]
+trait PowerManaged {
+    fn suspend(&self);
+    fn is_suspended(&self) -> bool;
+}
+
+#[derive(Default)]
+struct Lamp {
+    level: u8,
+    suspended: Cell<bool>,
+}
+
+#[tinydyn::provides(dyn Dimmable, dyn PowerManaged)]
+impl Device for Lamp {
+    fn name(&self) -> &str {
+        "lamp"
+    }
+}
+
+impl Dimmable for Lamp {
+    fn set_level(&mut self, level: u8) {
+        self.level = level;
+    }
+}
+
+impl PowerManaged for Lamp {
+    fn suspend(&self) {
+        self.suspended.set(true);
+    }
+
+    fn is_suspended(&self) -> bool {
+        self.suspended.get()
+    }
+}
+
+/// A device that borrows its name, so isn't `'static`.
+struct Sensor<'a>(&'a str);
+
+#[tinydyn::provides]
+impl Device for Sensor<'_> {
+    fn name(&self) -> &str {
+        self.0
+    }
+}
+
+#[test]
+fn query_provided() {
+    let lamp = Lamp::default();
+    let device: Ref<dyn Device> = Ref::new(&lamp);
+    assert!(device.provides::<dyn PowerManaged>());
+    let power = device.query::<dyn PowerManaged>().unwrap();
+    power.suspend();
+    assert!(power.is_suspended());
+    assert!(lamp.suspended.get());
+}
+
+#[test]
+fn query_missing() {
+    let name = String::from("thermometer");
+    let sensor = Sensor(&name);
+    let device: Ref<dyn Device> = Ref::new(&sensor);
+    assert_eq!(device.name(), "thermometer");
+    assert!(!device.provides::<dyn Dimmable>());
+    assert!(device.query::<dyn PowerManaged>().is_none());
+}
+
+#[test]
+fn query_mut() {
+    let mut lamp = Lamp::default();
+    let mut device: RefMut<dyn Device + Send> = RefMut::new(&mut lamp);
+    device.query_mut::<dyn Dimmable>().unwrap().set_level(50);
+    assert!(!device.query::<dyn PowerManaged>().unwrap().is_suspended());
+    assert_eq!(lamp.level, 50);
+}
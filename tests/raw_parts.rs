@@ -0,0 +1,67 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::ptr::NonNull;
+
+use tinydyn::{tinydyn, PlainDyn, Ref, RefMut};
+
+#[tinydyn]
+trait Counter {
+    fn get(&self) -> u32;
+    fn bump(&mut self);
+}
+
+impl Counter for u32 {
+    fn get(&self) -> u32 {
+        *self
+    }
+
+    fn bump(&mut self) {
+        *self += 1;
+    }
+}
+
+type Meta = <dyn Counter as PlainDyn>::Metadata;
+
+#[test]
+fn ref_round_trip() {
+    let x = 5u32;
+    let (data, meta): (NonNull<()>, Meta) = Ref::<dyn Counter>::new(&x).to_raw_parts();
+    assert_eq!(data, NonNull::from(&x).cast());
+    let y: Ref<dyn Counter> = unsafe { Ref::from_raw_parts(data, meta) };
+    assert_eq!(y.get(), 5);
+}
+
+#[test]
+fn shared_metadata() {
+    // Store data pointers separately from a single metadata.
+    let values = [1u32, 2, 3];
+    let meta = Ref::<dyn Counter>::new(&values[0]).metadata();
+    let total: u32 = values
+        .iter()
+        .map(|v| unsafe { Ref::<dyn Counter>::from_raw_parts(NonNull::from(v).cast(), meta) })
+        .map(|r| r.get())
+        .sum();
+    assert_eq!(total, 6);
+}
+
+#[test]
+fn ref_mut_round_trip() {
+    let mut x = 5u32;
+    let (data, meta) = RefMut::<dyn Counter>::new(&mut x).into_raw_parts();
+    let mut y: RefMut<dyn Counter + Send> = unsafe { RefMut::from_raw_parts(data, meta) };
+    y.bump();
+    assert_eq!(y.get(), 6);
+    assert_eq!(x, 6);
+}
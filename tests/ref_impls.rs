@@ -0,0 +1,108 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tinydyn::{tinydyn, Ref, RefMut};
+
+#[tinydyn(ref_impls)]
+trait Reader {
+    fn read(&self) -> u32;
+    fn read_twice(&self) -> u32 {
+        self.read() * 2
+    }
+}
+
+#[tinydyn(ref_impls)]
+trait Writer {
+    fn peek(&self) -> u32;
+    fn write(&mut self, value: u32);
+}
+
+// `Ref` implements `Debug`, so with `ref_impls` this blanket impl would
+// overlap with the generated `impl Describe for Ref<dyn Describe>`.
+#[tinydyn]
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+impl<T: core::fmt::Debug> Describe for T {
+    fn describe(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl Reader for u32 {
+    fn read(&self) -> u32 {
+        *self
+    }
+}
+
+impl Writer for u32 {
+    fn peek(&self) -> u32 {
+        *self
+    }
+
+    fn write(&mut self, value: u32) {
+        *self = value;
+    }
+}
+
+fn sum(readers: &[impl Reader]) -> u32 {
+    readers.iter().map(Reader::read).sum()
+}
+
+fn store(mut writer: impl Writer, value: u32) -> u32 {
+    writer.write(value);
+    writer.peek()
+}
+
+#[test]
+fn ref_by_value() {
+    let (a, b) = (1u32, 2u32);
+    let readers: Vec<Ref<dyn Reader>> = vec![Ref::new(&a), Ref::new(&b)];
+    assert_eq!(sum(&readers), 3);
+    assert_eq!(Reader::read_twice(&readers[1]), 4);
+
+    // A `Ref` is itself a concrete type that can be wrapped in another `Ref`.
+    let nested: Ref<dyn Reader> = Ref::new(&readers[0]);
+    assert_eq!(nested.read(), 1);
+}
+
+#[test]
+fn ref_mut_by_value() {
+    let mut x = 1u32;
+    let mut writer: RefMut<dyn Writer> = RefMut::new(&mut x);
+    assert_eq!(store(writer.as_mut(), 5), 5);
+    assert_eq!(writer.peek(), 5);
+
+    let mut nested: RefMut<dyn Writer> = RefMut::new(&mut writer);
+    nested.write(7);
+    assert_eq!(x, 7);
+}
+
+#[test]
+fn ref_to_mut_trait() {
+    // `Writer` has a `&mut self` method, so `Ref` calls `peek` through `Deref`.
+    let x = 4u32;
+    let writer: Ref<dyn Writer> = Ref::new(&x);
+    assert_eq!(writer.peek(), 4);
+}
+
+#[test]
+fn without_ref_impls() {
+    let x = 3u32;
+    let describe: Ref<dyn Describe> = Ref::new(&x);
+    assert_eq!((*describe).describe(), "3");
+    // Method-call syntax finds the blanket impl on `Ref` itself first.
+    assert!(describe.describe().starts_with("Ref"));
+}
@@ -0,0 +1,230 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::ffi::c_void;
+use core::ptr::NonNull;
+
+use tinydyn::{tinydyn, Ref, RefMut};
+
+#[tinydyn(repr_c)]
+pub trait Sensor {
+    fn read(&self) -> u32;
+    fn calibrate(&mut self, offset: i16, scale: &mut f32) -> bool;
+}
+
+struct Thermometer {
+    offset: i16,
+}
+
+impl Sensor for Thermometer {
+    fn read(&self) -> u32 {
+        (20 + self.offset) as u32
+    }
+
+    fn calibrate(&mut self, offset: i16, scale: &mut f32) -> bool {
+        self.offset = offset;
+        *scale = 2.0;
+        true
+    }
+}
+
+/// How C sees `SensorRefMut`.
+#[repr(C)]
+struct CSensorRefMut {
+    data: *mut c_void,
+    vtable: *const CSensorVtable,
+}
+
+#[repr(C)]
+struct CSensorVtable {
+    read: unsafe extern "C" fn(*const c_void) -> u32,
+    calibrate: unsafe extern "C" fn(*mut c_void, i16, *mut f32) -> bool,
+}
+
+#[test]
+fn call_as_c() {
+    let mut thermometer = Thermometer { offset: 0 };
+    let sensor = RefMut::<dyn Sensor>::new(&mut thermometer);
+    let c_sensor: CSensorRefMut = unsafe { core::mem::transmute(sensor) };
+    let mut scale = 1.0;
+    unsafe {
+        let vtable = &*c_sensor.vtable;
+        assert!((vtable.calibrate)(c_sensor.data, 5, &mut scale));
+        assert_eq!((vtable.read)(c_sensor.data), 25);
+    }
+    assert_eq!(scale, 2.0);
+    assert_eq!(thermometer.offset, 5);
+}
+
+#[test]
+fn sensor_header() {
+    assert_eq!(
+        SensorVtable::C_HEADER,
+        "\
+/* Generated by tinydyn for `dyn Sensor`. */
+#ifndef TINYDYN_SENSOR_H
+#define TINYDYN_SENSOR_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct SensorVtable {
+    uint32_t (*read)(const void *self);
+    bool (*calibrate)(void *self, int16_t offset, float *scale);
+} SensorVtable;
+
+typedef struct SensorRef {
+    const void *data;
+    const SensorVtable *vtable;
+} SensorRef;
+
+typedef struct SensorRefMut {
+    void *data;
+    const SensorVtable *vtable;
+} SensorRefMut;
+
+#endif /* TINYDYN_SENSOR_H */
+"
+    );
+}
+
+#[tinydyn(repr_c)]
+trait Callback {
+    fn call(&self, data: *const u8, len: usize);
+}
+
+impl Callback for u8 {
+    fn call(&self, _data: *const u8, _len: usize) {}
+}
+
+#[test]
+fn inline_header() {
+    assert!(CallbackVtable::C_HEADER
+        .contains("    void (*call)(const void *self, const uint8_t *data, size_t len);\n"));
+    assert!(
+        CallbackVtable::C_HEADER.contains("    const void *data;\n    CallbackVtable vtable;\n")
+    );
+    // The inline vtable is stored by value.
+    assert_eq!(
+        core::mem::size_of::<Ref<dyn Callback>>(),
+        2 * core::mem::size_of::<usize>()
+    );
+}
+
+#[tinydyn(repr_c)]
+pub trait Port {
+    fn write(&self, register: core::ffi::c_int, default: std::primitive::u8);
+    fn id(&self) -> NonNull<core::ffi::c_char>;
+}
+
+#[test]
+fn header_names() {
+    // Parameters named after C keywords are renamed, and full paths are accepted.
+    assert!(PortVtable::C_HEADER
+        .contains("    void (*write)(const void *self, int arg1, uint8_t arg2);\n"));
+    assert!(PortVtable::C_HEADER.contains("    char * (*id)(const void *self);\n"));
+}
+
+/// The state of the C driver.
+struct CState {
+    raw: u32,
+}
+
+unsafe extern "C" fn c_read(ctx: *const c_void) -> u32 {
+    unsafe { (*(ctx as *const CState)).raw }
+}
+
+unsafe extern "C" fn c_calibrate(ctx: *mut c_void, offset: i16, scale: *mut f32) -> bool {
+    unsafe {
+        (*(ctx as *mut CState)).raw += offset as u32;
+        *scale = 0.5;
+    }
+    false
+}
+
+static C_DRIVER: SensorCVtable = SensorCVtable {
+    read: Some(c_read),
+    calibrate: Some(c_calibrate),
+};
+
+#[test]
+fn import_from_c() {
+    let mut state = CState { raw: 7 };
+    let ctx = NonNull::from(&mut state).cast();
+    let mut sensor = unsafe { RefMut::<dyn Sensor>::from_c(ctx, &C_DRIVER) }.unwrap();
+    let mut scale = 1.0;
+    assert!(!sensor.calibrate(3, &mut scale));
+    assert_eq!(sensor.read(), 10);
+    assert_eq!(scale, 0.5);
+
+    let sensor = unsafe { Ref::<dyn Sensor>::from_c(ctx, &C_DRIVER) }.unwrap();
+    assert_eq!(sensor.read(), 10);
+}
+
+#[test]
+fn import_null_entry() {
+    static PARTIAL: SensorCVtable = SensorCVtable {
+        read: Some(c_read),
+        calibrate: None,
+    };
+    let state = CState { raw: 0 };
+    let ctx = NonNull::from(&state).cast();
+    assert!(unsafe { Ref::<dyn Sensor>::from_c(ctx, &PARTIAL) }.is_none());
+}
+
+#[test]
+fn import_inline() {
+    static CALLED: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+    unsafe extern "C" fn c_call(_ctx: *const c_void, _data: *const u8, len: usize) {
+        CALLED.store(len, core::sync::atomic::Ordering::Relaxed);
+    }
+    static C_CALLBACK: CallbackCVtable = CallbackCVtable { call: Some(c_call) };
+    let callback =
+        unsafe { Ref::<dyn Callback>::from_c(NonNull::dangling(), &C_CALLBACK) }.unwrap();
+    callback.call([1, 2, 3].as_ptr(), 3);
+    assert_eq!(CALLED.load(core::sync::atomic::Ordering::Relaxed), 3);
+}
+
+#[tinydyn(repr_c)]
+trait Tick {
+    fn tick(&self, now: u32) -> u32;
+}
+
+struct Blinker {
+    period: u32,
+}
+
+impl Tick for Blinker {
+    fn tick(&self, now: u32) -> u32 {
+        now % self.period
+    }
+}
+
+/// Stands in for a C API that calls back with its context.
+unsafe fn c_timer_fire(
+    cb: unsafe extern "C" fn(*const c_void, u32) -> u32,
+    ctx: *const c_void,
+    now: u32,
+) -> u32 {
+    unsafe { cb(ctx, now) }
+}
+
+#[test]
+fn into_c_callback() {
+    static BLINKER: Blinker = Blinker { period: 4 };
+    let (ctx, cb) = Ref::<dyn Tick + Sync>::new(&BLINKER).into_c_callback();
+    assert_eq!(ctx, &BLINKER as *const Blinker as *const c_void);
+    assert_eq!(unsafe { c_timer_fire(cb, ctx, 10) }, 2);
+}
@@ -0,0 +1,183 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::ffi::c_void;
+use core::ptr::NonNull;
+
+use tinydyn::{tinydyn, vtable, AbiHeader, Ref, StableAbi};
+
+#[tinydyn(stable_abi(version = 3, min_version = 2))]
+pub trait Services {
+    #[tinydyn(since = 1)]
+    fn version(&self) -> u32;
+
+    #[tinydyn(since = 2)]
+    fn put(&self, byte: u8) -> bool;
+
+    /// Older bootloaders can't reset, and return `code` unchanged.
+    #[tinydyn(since = 3)]
+    fn reset(&self, code: u32) -> u32 {
+        code
+    }
+}
+
+struct Bootloader;
+
+impl Services for Bootloader {
+    fn version(&self) -> u32 {
+        3
+    }
+
+    fn put(&self, byte: u8) -> bool {
+        byte != 0
+    }
+
+    fn reset(&self, code: u32) -> u32 {
+        code + 1
+    }
+}
+
+/// A version 2 service table, as an older bootloader built it.
+#[repr(C)]
+struct ServicesV2 {
+    header: AbiHeader,
+    version: extern "C" fn(*const c_void) -> u32,
+    put: extern "C" fn(*const c_void, u8) -> bool,
+}
+
+extern "C" fn old_version(_ctx: *const c_void) -> u32 {
+    2
+}
+
+extern "C" fn old_put(_ctx: *const c_void, byte: u8) -> bool {
+    byte == 0
+}
+
+static SERVICES_V2: ServicesV2 = ServicesV2 {
+    header: AbiHeader {
+        size: size_of::<ServicesV2>() as u32,
+        version: 2,
+    },
+    version: old_version,
+    put: old_put,
+};
+
+fn import_v2() -> Ref<'static, dyn Services> {
+    let vtable = NonNull::from(&SERVICES_V2).cast();
+    unsafe { Ref::try_from_versioned(NonNull::dangling(), vtable) }.unwrap()
+}
+
+#[test]
+fn current_version() {
+    static BOOTLOADER: Bootloader = Bootloader;
+    let vtable: &ServicesVtable = vtable!(dyn Services for Bootloader);
+    let ctx = NonNull::from(&BOOTLOADER).cast();
+    let services =
+        unsafe { Ref::<dyn Services>::try_from_versioned(ctx, NonNull::from(vtable)) }.unwrap();
+    assert_eq!(services.version(), 3);
+    assert!(services.put(1));
+    assert_eq!(services.reset(4), 5);
+    assert_eq!(services.try_reset(4), Some(5));
+    assert_eq!(<dyn Services as StableAbi>::VERSION, 3);
+}
+
+#[test]
+fn older_version() {
+    let services = import_v2();
+    assert_eq!(services.version(), 2);
+    assert!(services.put(0));
+    assert_eq!(services.try_reset(4), None);
+    // Missing from the older vtable, so this runs the default body.
+    assert_eq!(services.reset(4), 4);
+}
+
+#[test]
+fn too_old() {
+    /// A version 1 table is older than `min_version`.
+    #[repr(C)]
+    struct ServicesV1 {
+        header: AbiHeader,
+        version: extern "C" fn(*const c_void) -> u32,
+    }
+    static SERVICES_V1: ServicesV1 = ServicesV1 {
+        header: AbiHeader {
+            size: size_of::<ServicesV1>() as u32,
+            version: 1,
+        },
+        version: old_version,
+    };
+    let vtable = NonNull::from(&SERVICES_V1).cast();
+    let services = unsafe { Ref::<dyn Services>::try_from_versioned(NonNull::dangling(), vtable) };
+    assert!(services.is_none());
+
+    // A table claiming a newer version must still be large enough.
+    static TRUNCATED: AbiHeader = AbiHeader {
+        size: size_of::<AbiHeader>() as u32,
+        version: 4,
+    };
+    let vtable = NonNull::from(&TRUNCATED).cast();
+    let services = unsafe { Ref::<dyn Services>::try_from_versioned(NonNull::dangling(), vtable) };
+    assert!(services.is_none());
+}
+
+/// A stable vtable keeps its header even with a single method.
+#[tinydyn(stable_abi(version = 1))]
+pub trait Ping {
+    #[tinydyn(since = 1)]
+    fn ping(&self) -> u32;
+}
+
+impl Ping for Bootloader {
+    fn ping(&self) -> u32 {
+        7
+    }
+}
+
+#[test]
+fn single_method() {
+    static BOOTLOADER: Bootloader = Bootloader;
+    let vtable: &PingVtable = vtable!(dyn Ping for Bootloader);
+    let ctx = NonNull::from(&BOOTLOADER).cast();
+    let ping = unsafe { Ref::<dyn Ping>::try_from_versioned(ctx, NonNull::from(vtable)) }.unwrap();
+    assert_eq!(ping.ping(), 7);
+    assert_eq!(size_of::<Ref<dyn Ping>>(), 2 * size_of::<usize>());
+}
+
+#[test]
+fn services_header() {
+    assert!(ServicesVtable::C_HEADER.contains(
+        "typedef struct ServicesVtable {\n    \
+        TinydynAbiHeader header;\n    \
+        uint32_t (*version)(const void *self);\n"
+    ));
+}
+
+/// Checks the headers with a C compiler, including two in one file.
+#[test]
+#[cfg(all(unix, not(miri)))]
+fn headers_compile() {
+    let source = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("stable_abi_headers.c");
+    std::fs::write(
+        &source,
+        [ServicesVtable::C_HEADER, PingVtable::C_HEADER].concat(),
+    )
+    .unwrap();
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+    let status = std::process::Command::new(cc)
+        .args(["-std=c99", "-Wall", "-Werror", "-fsyntax-only"])
+        .arg(&source)
+        .status()
+        .expect("checking the generated headers needs a C compiler");
+    assert!(status.success());
+}
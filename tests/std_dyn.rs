@@ -0,0 +1,110 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt::Debug;
+
+use tinydyn::{tinydyn, Ref, RefMut};
+
+#[tinydyn(downcast)]
+trait Counter: Debug {
+    fn get(&self) -> u32;
+    fn add(&mut self, n: u32) -> u32;
+    fn name(&self) -> &str;
+}
+
+#[tinydyn]
+trait Single {
+    fn value(&self) -> i32;
+}
+
+#[derive(Debug)]
+struct Tally {
+    count: u32,
+}
+
+impl Counter for Tally {
+    fn get(&self) -> u32 {
+        self.count
+    }
+
+    fn add(&mut self, n: u32) -> u32 {
+        self.count += n;
+        self.count
+    }
+
+    fn name(&self) -> &str {
+        "tally"
+    }
+}
+
+impl Single for Tally {
+    fn value(&self) -> i32 {
+        self.count as i32
+    }
+}
+
+fn std_get(counter: &dyn Counter) -> u32 {
+    counter.get()
+}
+
+fn std_add(counter: &mut dyn Counter, n: u32) -> u32 {
+    counter.add(n)
+}
+
+#[test]
+fn from_std_dyn() {
+    let mut x = Tally { count: 1 };
+    let std_ref: &dyn Counter = &x;
+    let counter: Ref<dyn Counter> = Ref::from_std_dyn(&std_ref);
+    assert_eq!(counter.get(), 1);
+    assert_eq!(counter.name(), "tally");
+    assert_eq!(format!("{counter:?}"), "Tally { count: 1 }");
+    // It wraps the `&dyn Counter`, not a `Tally`.
+    assert!(counter.downcast_ref::<Tally>().is_none());
+
+    let mut std_mut: &mut dyn Counter = &mut x;
+    let mut counter: RefMut<dyn Counter> = RefMut::from_std_dyn(&mut std_mut);
+    assert_eq!(counter.add(2), 3);
+    assert_eq!(counter.get(), 3);
+    assert_eq!(x.count, 3);
+
+    let single: &dyn Single = &x;
+    assert_eq!(Ref::<dyn Single>::from_std_dyn(&single).value(), 3);
+}
+
+#[test]
+fn as_std_dyn() {
+    let mut x = Tally { count: 1 };
+    // `Counter` has a `&mut self` method, so only the adapter of a `RefMut` implements it.
+    let mut counter: RefMut<dyn Counter> = RefMut::new(&mut x);
+    let mut adapter = counter.as_std_dyn();
+    assert_eq!(std_get(&adapter), 1);
+    assert_eq!(format!("{adapter:?}"), "Tally { count: 1 }");
+    assert_eq!(std_add(&mut adapter, 2), 3);
+    assert_eq!(counter.get(), 3);
+
+    let single: Ref<dyn Single> = Ref::new(&x);
+    let single: &dyn Single = &single.as_std_dyn();
+    assert_eq!(single.value(), 3);
+}
+
+#[test]
+fn round_trip() {
+    let mut x = Tally { count: 5 };
+    let mut counter: RefMut<dyn Counter> = RefMut::new(&mut x);
+    let adapter = counter.as_std_dyn();
+    let std_ref: &dyn Counter = &adapter;
+    let counter: Ref<dyn Counter> = Ref::from_std_dyn(&std_ref);
+    assert_eq!(counter.get(), 5);
+}
@@ -0,0 +1,86 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+
+use tinydyn::{combine, tinydyn, Ref, RefMut};
+
+#[tinydyn]
+trait Service: Send + Sync + 'static {
+    fn call(&self) -> u32;
+}
+
+#[tinydyn]
+trait Counter: core::marker::Send {
+    fn bump(&mut self) -> u32;
+}
+
+impl Service for AtomicU32 {
+    fn call(&self) -> u32 {
+        self.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl Counter for u32 {
+    fn bump(&mut self) -> u32 {
+        *self += 1;
+        *self
+    }
+}
+
+combine!(trait CountingService = Service + Counter);
+
+impl Counter for AtomicU32 {
+    fn bump(&mut self) -> u32 {
+        *self.get_mut() += 1;
+        *self.get_mut()
+    }
+}
+
+fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+#[test]
+fn shared_across_threads() {
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+    let service: Ref<'static, dyn Service> = Ref::new(&COUNT);
+    assert_send_sync(&service);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(move || service.call());
+        }
+    });
+    assert_eq!(service.call(), 4);
+}
+
+#[test]
+fn sent_to_thread() {
+    let mut x = 5u32;
+    let mut counter: RefMut<dyn Counter> = RefMut::new(&mut x);
+    thread::scope(|s| {
+        s.spawn(|| assert_eq!(counter.bump(), 6));
+    });
+    assert_eq!(counter.bump(), 7);
+}
+
+#[test]
+fn combined() {
+    let mut x = AtomicU32::new(1);
+    let mut both: RefMut<dyn CountingService> = RefMut::new(&mut x);
+    assert_eq!(both.call(), 1);
+    assert_eq!(both.bump(), 3);
+    thread::scope(|s| {
+        s.spawn(|| assert_eq!(both.call(), 3));
+    });
+}
@@ -0,0 +1,104 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::ptr::NonNull;
+
+use tinydyn::{tinydyn, vtable, vtable_for, PlainDyn, Ref, VTable};
+
+#[tinydyn]
+trait Shape {
+    fn area(&self) -> u32;
+    fn sides(&self) -> u32;
+}
+
+#[tinydyn]
+trait Named {
+    fn name(&self) -> &'static str;
+}
+
+struct Square(u32);
+struct Triangle;
+
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+
+    fn sides(&self) -> u32 {
+        4
+    }
+}
+
+impl Shape for Triangle {
+    fn area(&self) -> u32 {
+        1
+    }
+
+    fn sides(&self) -> u32 {
+        3
+    }
+}
+
+impl Named for Square {
+    fn name(&self) -> &'static str {
+        "square"
+    }
+}
+
+impl Named for Triangle {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+}
+
+static SQUARE_SHAPE: &ShapeVtable = vtable!(dyn Shape for Square);
+
+#[test]
+fn vtable_names() {
+    let a: &VTable<dyn Shape + Send + Sync> = vtable_for::<dyn Shape, Square>();
+    let b: &ShapeVtable = vtable!(dyn Shape + Sync for Square);
+    assert_eq!(a, b);
+    assert_eq!(a, SQUARE_SHAPE);
+    assert_ne!(a, vtable!(dyn Shape for Triangle));
+    assert_ne!(
+        vtable!(dyn Named for Square),
+        vtable!(dyn Named for Triangle)
+    );
+}
+
+#[test]
+fn which_implementation() {
+    let shape = Ref::<dyn Shape>::new(&Square(2));
+    assert_eq!(
+        shape.metadata(),
+        <dyn Shape>::metadata_from_vtable(SQUARE_SHAPE)
+    );
+    let named = Ref::<dyn Named>::new(&Triangle);
+    assert_eq!(
+        named.metadata(),
+        <dyn Named>::metadata_from_vtable(vtable!(dyn Named for Triangle))
+    );
+}
+
+#[test]
+fn shared_vtable() {
+    let squares = [Square(1), Square(2), Square(3)];
+    let meta = <dyn Shape>::metadata_from_vtable(SQUARE_SHAPE);
+    let total: u32 = squares
+        .iter()
+        .map(|s| unsafe { Ref::<dyn Shape>::from_raw_parts(NonNull::from(s).cast(), meta) })
+        .map(|r| r.area() + r.sides())
+        .sum();
+    assert_eq!(total, 14 + 12);
+}
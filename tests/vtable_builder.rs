@@ -0,0 +1,138 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::OnceLock;
+
+use tinydyn::{tinydyn, Ref, RefMut, VTableFor};
+
+#[tinydyn]
+trait Handler {
+    fn name(&self) -> &str;
+    fn pick<'a>(&self, a: &'a str, b: &'a str) -> &'a str;
+    fn handle(&mut self, event: u32) -> u32;
+}
+
+struct Real {
+    handled: u32,
+}
+
+impl Handler for Real {
+    fn name(&self) -> &str {
+        "real"
+    }
+
+    fn pick<'a>(&self, a: &'a str, _b: &'a str) -> &'a str {
+        a
+    }
+
+    fn handle(&mut self, event: u32) -> u32 {
+        self.handled += event;
+        self.handled
+    }
+}
+
+struct Context {
+    name: String,
+    events: Vec<u32>,
+}
+
+fn context_name(ctx: &Context) -> &str {
+    &ctx.name
+}
+
+static CONTEXT_HANDLER: VTableFor<dyn Handler, Context> = HandlerVtable::builder::<Context>()
+    .name(context_name)
+    .pick(|_, _, b| b)
+    .handle(|ctx, event| {
+        ctx.events.push(event);
+        ctx.events.len() as u32
+    })
+    .build()
+    .unwrap();
+
+#[test]
+fn from_functions() {
+    let mut ctx = Context {
+        name: "ctx".to_string(),
+        events: Vec::new(),
+    };
+    let handler = Ref::from_vtable(&ctx, &CONTEXT_HANDLER);
+    assert_eq!(handler.name(), "ctx");
+    assert_eq!(handler.pick("a", "b"), "b");
+
+    let mut handler = RefMut::from_vtable(&mut ctx, &CONTEXT_HANDLER);
+    assert_eq!(handler.handle(3), 1);
+    assert_eq!(handler.handle(4), 2);
+    assert_eq!(ctx.events, [3, 4]);
+}
+
+#[test]
+fn missing_method() {
+    let builder = HandlerVtable::builder::<Context>()
+        .name(context_name)
+        .pick(|_, a, _| a);
+    assert!(builder.build().is_none());
+}
+
+#[test]
+fn override_impl() {
+    static FAKE: VTableFor<dyn Handler, Real> = HandlerVtable::builder_from(VTableFor::of_impl())
+        .name(|_| "fake")
+        .build()
+        .unwrap();
+    let mut real = Real { handled: 0 };
+    let mut handler = RefMut::from_vtable(&mut real, &FAKE);
+    assert_eq!(handler.name(), "fake");
+    assert_eq!(handler.pick("a", "b"), "a");
+    assert_eq!(handler.handle(5), 5);
+}
+
+#[test]
+fn assembled_at_runtime() {
+    static PATCHED: OnceLock<VTableFor<dyn Handler, Real>> = OnceLock::new();
+    let patched = PATCHED.get_or_init(|| {
+        HandlerVtable::builder_from(VTableFor::<_, Real>::of_impl())
+            .name(|_| "patched")
+            .build()
+            .unwrap()
+    });
+    let real = Real { handled: 0 };
+    assert_eq!(Ref::from_vtable(&real, patched).name(), "patched");
+}
+
+#[tinydyn(downcast, eq)]
+trait Key {
+    fn key(&self) -> u32;
+}
+
+#[derive(PartialEq, Eq)]
+struct Id(u32);
+
+impl Key for Id {
+    fn key(&self) -> u32 {
+        self.0
+    }
+}
+
+#[test]
+fn with_options() {
+    static DOUBLED: VTableFor<dyn Key, Id> = KeyVtable::builder::<Id>()
+        .key(|id| id.0 * 2)
+        .build()
+        .unwrap();
+    let a = Ref::from_vtable(&Id(4), &DOUBLED);
+    assert_eq!(a.key(), 8);
+    assert_eq!(a.downcast_ref::<Id>().map(|id| id.0), Some(4));
+    assert!(a == Ref::<dyn Key>::new(&Id(4)));
+}